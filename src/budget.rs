@@ -0,0 +1,230 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::Users;
+
+/// What a budget limits.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[serde(tag = "scope", content = "name", rename_all = "snake_case")]
+pub enum BudgetScope {
+    /// Everything the group spends.
+    Group,
+    /// The expenses of one category.
+    Category(String),
+    /// One member's shares of the expenses.
+    Member(String),
+    /// Every member's shares, each on their own.
+    EachMember,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Budget {
+    scope: BudgetScope,
+    limit: f64,
+}
+
+/// The budgets of a group and when to warn about them.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct BudgetConfig {
+    /// Share of a budget used up after which it is flagged, 0.8 for 80%.
+    alert_at: f64,
+    budgets: Vec<Budget>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        BudgetConfig {
+            alert_at: 0.8,
+            budgets: Vec::new(),
+        }
+    }
+}
+
+impl BudgetConfig {
+    /// Takes over the budgets `theirs` has and we do not, and their alert
+    /// threshold if ours is still the default.
+    pub(crate) fn fill_in(&mut self, theirs: &BudgetConfig) {
+        for budget in &theirs.budgets {
+            if !self.budgets.iter().any(|b| b.scope == budget.scope) {
+                self.budgets.push(budget.clone());
+            }
+        }
+        if self.alert_at == BudgetConfig::default().alert_at {
+            self.alert_at = theirs.alert_at;
+        }
+    }
+
+    /// What is wrong with the budgets of a group file, such as a limit of
+    /// 0 that nothing can be measured against.
+    pub(crate) fn errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !(self.alert_at > 0.0 && self.alert_at <= 1.0) {
+            errors.push(format!(
+                "budget alert at {}% is not between 0 and 100",
+                self.alert_at * 100.0
+            ));
+        }
+        for budget in &self.budgets {
+            if !(budget.limit.is_finite() && budget.limit > 0.0) {
+                errors.push(format!(
+                    "budget {}: limit {} is not positive",
+                    budget.scope.label(),
+                    budget.limit
+                ));
+            }
+        }
+        errors
+    }
+}
+
+/// How far along a budget is.
+#[derive(Serialize, Clone, Debug)]
+pub struct BudgetStatus {
+    /// E.g. `group`, `category food` or `member Ali`.
+    pub label: String,
+    pub limit: f64,
+    pub spent: f64,
+    /// Whether `spent` crossed the alert threshold.
+    pub alert: bool,
+}
+
+impl BudgetStatus {
+    pub fn used(&self) -> f64 {
+        self.spent / self.limit
+    }
+
+    /// One line description, e.g. `category food: 250.00 of 300.00 (83%)`.
+    pub fn summary(&self) -> String {
+        format!(
+            "{}: {:.2} of {:.2} ({:.0}%)",
+            self.label,
+            self.spent,
+            self.limit,
+            self.used() * 100.0
+        )
+    }
+}
+
+impl BudgetScope {
+    /// Parses `group`, `category <name>`, `member <name>` or `each`.
+    pub fn parse(kind: &str, name: Option<String>) -> Result<Self, String> {
+        match (kind, name) {
+            ("group", None) => Ok(BudgetScope::Group),
+            ("each", None) => Ok(BudgetScope::EachMember),
+            ("category", Some(name)) => Ok(BudgetScope::Category(name)),
+            ("member", Some(name)) => Ok(BudgetScope::Member(name)),
+            ("category" | "member", None) => Err(format!("Missing {} name", kind)),
+            _ => Err(format!("Unknown budget: {}", kind)),
+        }
+    }
+
+    /// Whether a budget of this kind is followed by a name.
+    pub fn takes_name(kind: &str) -> bool {
+        matches!(kind, "category" | "member")
+    }
+
+    fn label(&self) -> String {
+        match self {
+            BudgetScope::Group => "group".to_string(),
+            BudgetScope::Category(name) => format!("category {}", name),
+            BudgetScope::Member(name) => format!("member {}", name),
+            BudgetScope::EachMember => "each member".to_string(),
+        }
+    }
+}
+
+impl Users {
+    /// Sets the budget for `scope`, replacing the one it had.
+    pub fn set_budget(&mut self, scope: BudgetScope, limit: f64) -> Result<(), String> {
+        if !limit.is_finite() || limit <= 0.0 {
+            return Err(format!("Invalid budget: {}", limit));
+        }
+        if let BudgetScope::Member(name) = &scope
+            && !self.is_member(name)
+        {
+            return Err(format!("User {} not found", name));
+        }
+        if let BudgetScope::Category(name) = &scope
+            && name.trim().is_empty()
+        {
+            return Err("The category name is empty".to_string());
+        }
+        self.budgets.budgets.retain(|b| b.scope != scope);
+        self.budgets.budgets.push(Budget { scope, limit });
+        Ok(())
+    }
+
+    pub fn remove_budget(&mut self, scope: &BudgetScope) -> Result<(), String> {
+        if !self.budgets.budgets.iter().any(|b| b.scope == *scope) {
+            return Err(format!("There is no budget for {}", scope.label()));
+        }
+        self.budgets.budgets.retain(|b| b.scope != *scope);
+        Ok(())
+    }
+
+    /// Flags budgets once `percent` of them is used up.
+    pub fn set_budget_alert(&mut self, percent: f64) -> Result<(), String> {
+        if !(percent > 0.0 && percent <= 100.0) {
+            return Err(format!("Invalid percentage: {}", percent));
+        }
+        self.budgets.alert_at = percent / 100.0;
+        Ok(())
+    }
+
+    pub fn budget_alert(&self) -> f64 {
+        self.budgets.alert_at * 100.0
+    }
+
+    /// Every budget against what was spent so far, settled expenses
+    /// included. A budget for each member gives a line per member.
+    pub fn budget_status(&self) -> Vec<BudgetStatus> {
+        let report = self.report();
+        let consumed = |name: &str| {
+            report
+                .members
+                .iter()
+                .find(|m| m.name == name)
+                .map_or(0.0, |m| m.consumed)
+        };
+        let mut statuses = Vec::new();
+        for budget in &self.budgets.budgets {
+            let spent = match &budget.scope {
+                BudgetScope::Group => vec![(budget.scope.label(), report.total)],
+                BudgetScope::Category(name) => vec![(
+                    budget.scope.label(),
+                    report
+                        .categories
+                        .iter()
+                        .find(|c| c.name == *name)
+                        .map_or(0.0, |c| c.spent),
+                )],
+                BudgetScope::Member(name) => vec![(budget.scope.label(), consumed(name))],
+                BudgetScope::EachMember => self
+                    .users
+                    .iter()
+                    .map(|u| (format!("member {}", u.name), consumed(&u.name)))
+                    .collect(),
+            };
+            for (label, spent) in spent {
+                statuses.push(BudgetStatus {
+                    label,
+                    limit: budget.limit,
+                    spent,
+                    alert: spent >= budget.limit * self.budgets.alert_at,
+                });
+            }
+        }
+        statuses
+    }
+
+    /// The budgets past the alert threshold, the most used up first.
+    pub fn budget_alerts(&self) -> Vec<BudgetStatus> {
+        let mut alerts: Vec<BudgetStatus> = self
+            .budget_status()
+            .into_iter()
+            .filter(|s| s.alert)
+            .collect();
+        alerts.sort_by(|a, b| b.used().total_cmp(&a.used()));
+        alerts
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use crate::{Participant, Transaction, Users};
+
+/// Builds a transaction for a group, checking every part against the group
+/// as it is added, so that what `build` returns can be recorded as is.
+///
+/// Without weights or amounts everyone takes part equally. Members left
+/// out of the weights carry no weight, the ones left out of the amounts
+/// owe nothing.
+pub struct TransactionBuilder<'a> {
+    users: &'a Users,
+    amount: f64,
+    payer: String,
+    description: String,
+    category: Option<String>,
+    date: Option<String>,
+    weights: BTreeMap<String, u8>,
+    amounts: BTreeMap<String, f64>,
+}
+
+impl<'a> TransactionBuilder<'a> {
+    pub fn new(users: &'a Users, payer: &str, amount: f64) -> Result<Self, String> {
+        crate::check_amount(amount)?;
+        let payer = member(users, payer)?;
+        Ok(TransactionBuilder {
+            users,
+            amount,
+            payer,
+            description: String::new(),
+            category: None,
+            date: None,
+            weights: BTreeMap::new(),
+            amounts: BTreeMap::new(),
+        })
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_category(mut self, category: &str) -> Self {
+        self.category = Some(category.to_string());
+        self
+    }
+
+    /// The day of the expense as `YYYY-MM-DD`.
+    pub fn with_date(mut self, date: &str) -> Result<Self, String> {
+        let Some(date) = crate::import::parse_date(date, "%Y-%m-%d") else {
+            return Err(format!("Invalid date: {}, expected YYYY-MM-DD", date));
+        };
+        self.date = Some(date);
+        Ok(self)
+    }
+
+    /// Splits by share, `name` carrying `weight` of them.
+    pub fn with_weight(mut self, name: &str, weight: u8) -> Result<Self, String> {
+        if !self.amounts.is_empty() {
+            return Err("The transaction is already split by amount".to_string());
+        }
+        self.weights.insert(member(self.users, name)?, weight);
+        Ok(self)
+    }
+
+    /// Splits by amount, `name` owing `amount` of it.
+    pub fn with_amount(mut self, name: &str, amount: f64) -> Result<Self, String> {
+        if !self.weights.is_empty() {
+            return Err("The transaction is already split by share".to_string());
+        }
+        // A refund is owed back in negative amounts.
+        if !amount.is_finite() || amount * self.amount < 0.0 {
+            return Err(format!("Invalid amount: {}", amount));
+        }
+        self.amounts.insert(member(self.users, name)?, amount);
+        Ok(self)
+    }
+
+    /// The transaction over every member, with the shares worked out.
+    pub fn build(self) -> Result<Transaction, String> {
+        let participants = self
+            .users
+            .users
+            .iter()
+            .map(|u| {
+                if !self.amounts.is_empty() {
+                    Participant::with_share(
+                        &u.name,
+                        self.amounts.get(&u.name).copied().unwrap_or(0.0),
+                    )
+                } else if self.weights.is_empty() {
+                    Participant::new(&u.name, 1)
+                } else {
+                    Participant::new(&u.name, self.weights.get(&u.name).copied().unwrap_or(0))
+                }
+            })
+            .collect();
+        if !self.weights.is_empty() && self.weights.values().all(|w| *w == 0) {
+            return Err("Nobody carries any weight".to_string());
+        }
+
+        let mut transaction = Transaction::new(self.amount, self.payer, participants)
+            .with_description(&self.description)
+            .with_category(self.category)
+            .with_date(self.date);
+        crate::calculate_fair_shares(&mut transaction)?;
+        Ok(transaction)
+    }
+}
+
+fn member(users: &Users, name: &str) -> Result<String, String> {
+    if users.is_member(name) {
+        Ok(name.to_string())
+    } else {
+        Err(format!("User {} not found", name))
+    }
+}
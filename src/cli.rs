@@ -0,0 +1,787 @@
+use std::io::Write;
+
+use zeroize::Zeroizing;
+
+use crate::budget::BudgetScope;
+use crate::builder::TransactionBuilder;
+use crate::export::ExportFormat;
+use crate::import::ImportProfile;
+use crate::storage::{GroupLock, Storage};
+use crate::{Participant, Preset, SplitType, Users};
+
+const DEFAULT_FILE: &str = "dangi-dongi.json";
+
+const USAGE: &str = "Usage: dangi-dongi [--file <path>] [command]
+       dangi-dongi [--file <path>] [--peer <host[:port]>] [--listen <host:port>]
+                   [--theme <dark|light|high-contrast|monochrome>]
+
+Without a command the TUI is started. Files ending in .db, .sqlite or
+.sqlite3 are SQLite databases, anything else a JSON group file. The TUI
+syncs with the group on --peer, and answers peers syncing with it on
+--listen. Its keys are read from $XDG_CONFIG_HOME/dangi-dongi/keys.json,
+or DANGI_DONGI_KEYS, press '?' in the TUI to list them. The theme is
+taken from --theme, else from \"theme\" in dangi-dongi/config.json (or
+DANGI_DONGI_CONFIG), else it is monochrome if NO_COLOR is set.
+
+The passphrase of an encrypted group file is asked for when it is opened,
+or read from DANGI_DONGI_PASSPHRASE. `encrypt` asks for the new one, or
+reads it from DANGI_DONGI_NEW_PASSPHRASE. Only JSON group files can be
+encrypted, not SQLite ones.
+
+Peers only sync when they share a secret, given with --secret or in
+DANGI_DONGI_SYNC_SECRET, and the group is encrypted with it on the way.
+`sync listen` makes one up when none is given, and only listens on this
+machine unless --address is given.
+
+Commands:
+  add-user <name>
+  add-expense --payer <name> --amount <amount> [--description <text>]
+              [--category <name>] [--shares <name:weight,...>]
+              [--date <YYYY-MM-DD>]
+  add-expense --preset <name> [--amount <amount>] [--payer <name>]
+  preset add <name> [--payer <name>] [--participants <name[:weight],...>]
+             [--category <name>] [--amount <amount>]
+  preset list
+  preset remove <name>
+  import <csv file> --profile <name> [--payer <name>]
+  import-profile add <name> --date <column> --description <column>
+                     --amount <column> [--date-format <format>]
+                     [--delimiter <char>] [--no-header] [--negate]
+                     [--decimal-comma] [--payer <name>]
+  import-profile rule <name> <pattern> <preset>
+  import-profile list
+  import-app <splitwise|tricount> <csv file>
+  migrate-storage <from file> <to file>
+  merge <ancestor file> <our file> <their file> [--output <path>]
+  backups
+  verify
+  encrypt
+  decrypt
+  export <csv|markdown|html|ledger|beancount> [--output <path> [--force]]
+  report [--format <text|csv|json>] [--output <path> [--force]]
+  account set <member> <account>
+  account currency <code>
+  account list
+  budget set <group|each|category <name>|member <name>> <amount>
+  budget remove <group|each|category <name>|member <name>>
+  budget alert <percent>
+  budget status
+  serve [--address <host:port>] [--token <token>]
+  sync <host[:port]> [--secret <secret>]
+  sync listen [--address <host:port>] [--secret <secret>]
+  schema
+  member <name>
+  balances
+  help";
+
+/// The options that take no value.
+const FLAGS: &[&str] = &["no-header", "negate", "decimal-comma", "force"];
+
+/// Small helper for picking options and positionals out of the arguments.
+struct Args {
+    items: Vec<String>,
+}
+
+impl Args {
+    fn new(items: Vec<String>) -> Self {
+        Args { items }
+    }
+
+    /// Takes `--name <value>` out of the arguments.
+    fn option(&mut self, name: &str) -> Result<Option<String>, String> {
+        let flag = format!("--{}", name);
+        match self.items.iter().position(|a| *a == flag) {
+            Some(i) if i + 1 < self.items.len() => {
+                let value = self.items.remove(i + 1);
+                self.items.remove(i);
+                Ok(Some(value))
+            }
+            Some(_) => Err(format!("Missing value for {}", flag)),
+            None => Ok(None),
+        }
+    }
+
+    /// Takes `--name` out of the arguments, returning whether it was there.
+    fn flag(&mut self, name: &str) -> bool {
+        let flag = format!("--{}", name);
+        match self.items.iter().position(|a| *a == flag) {
+            Some(i) => {
+                self.items.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn amount(&mut self, name: &str) -> Result<Option<f64>, String> {
+        match self.option(name)? {
+            Some(v) => {
+                let amount = v
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid amount: {}", v))?;
+                crate::check_amount(amount)?;
+                Ok(Some(amount))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Takes the first argument that is neither an option nor the value of
+    /// one.
+    fn positional(&mut self) -> Option<String> {
+        let mut i = 0;
+        while i < self.items.len() {
+            let item = &self.items[i];
+            if !item.starts_with("--") {
+                return Some(self.items.remove(i));
+            }
+            i += if FLAGS.contains(&&item[2..]) { 1 } else { 2 };
+        }
+        None
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.items.first() {
+            Some(a) => Err(format!("Unexpected argument: {}", a)),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn run(args: Vec<String>) -> Result<(), String> {
+    let mut args = Args::new(args);
+    let file = args.option("file")?.unwrap_or(DEFAULT_FILE.to_string());
+    let peer = args.option("peer")?;
+    let listen = args.option("listen")?;
+    let secret = args
+        .option("secret")?
+        .or(std::env::var("DANGI_DONGI_SYNC_SECRET").ok());
+    let theme = args.option("theme")?;
+
+    let command = match args.positional() {
+        Some(c) => c,
+        None => {
+            args.finish()?;
+            let theme = crate::theme::Theme::load(theme.as_deref())?;
+            let key = match secret {
+                Some(secret) if peer.is_some() || listen.is_some() => {
+                    Some(crate::crypto::Key::for_sync(&secret)?)
+                }
+                None if peer.is_some() || listen.is_some() => {
+                    return Err("--peer and --listen need --secret".to_string());
+                }
+                _ => None,
+            };
+            return crate::tui::start_tui_with_sync(&file, peer, listen, key, theme)
+                .map_err(|e| e.to_string());
+        }
+    };
+    if peer.is_some() || listen.is_some() {
+        return Err("--peer and --listen are for the TUI, use the sync command".to_string());
+    }
+    if theme.is_some() {
+        return Err("--theme is for the TUI".to_string());
+    }
+
+    if command == "help" {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if command == "schema" {
+        args.finish()?;
+        println!("{}", crate::format::schema());
+        return Ok(());
+    }
+    if command == "migrate-storage" {
+        return migrate_storage(&mut args);
+    }
+    if command == "import-app" {
+        return import_app(&file, &mut args);
+    }
+    if command == "merge" {
+        return merge(&mut args);
+    }
+    if command == "verify" {
+        args.finish()?;
+        return verify(&file);
+    }
+
+    let _lock = GroupLock::acquire(&file)?;
+    let storage = open_storage(&file)?;
+    let mut users = open(storage.as_ref())?;
+    let before = crate::format::to_json(&users)?;
+    match command.as_str() {
+        "add-user" => {
+            let name = args.positional().ok_or("Missing user name")?;
+            args.finish()?;
+            users.add_member(&name)?;
+        }
+        "add-expense" => add_expense(&mut users, &mut args)?,
+        "preset" => preset(&mut users, &mut args)?,
+        "import" => import(&mut users, &mut args)?,
+        "import-profile" => import_profile(&mut users, &mut args)?,
+        "export" => {
+            let format = args.positional().ok_or("Missing export format")?;
+            let format =
+                ExportFormat::parse(&format).ok_or(format!("Unknown export format: {}", format))?;
+            let output = args.option("output")?;
+            let force = args.flag("force");
+            args.finish()?;
+            let report = users.export(format);
+            match output {
+                Some(path) => write_output(&path, &report, force)?,
+                None => print!("{}", report),
+            }
+        }
+        "report" => {
+            let format = args.option("format")?.unwrap_or("text".to_string());
+            let format = crate::reports::ReportFormat::parse(&format)
+                .ok_or(format!("Unknown report format: {}", format))?;
+            let output = args.option("output")?;
+            let force = args.flag("force");
+            args.finish()?;
+            let report = users.report().render(format);
+            match output {
+                Some(path) => write_output(&path, &report, force)?,
+                None => print!("{}", report),
+            }
+        }
+        "account" => account(&mut users, &mut args)?,
+        "budget" => budget(&mut users, &mut args)?,
+        "backups" => {
+            args.finish()?;
+            for backup in crate::storage::backups(&file) {
+                println!("{}", backup.display());
+            }
+            return Ok(());
+        }
+        "encrypt" | "decrypt" => {
+            args.finish()?;
+            if crate::storage::is_sqlite(&file) {
+                return Err(
+                    "Only JSON group files can be encrypted, migrate-storage the group to one first"
+                        .to_string(),
+                );
+            }
+            if !storage.exists() {
+                return Err(format!("{} does not exist", file));
+            }
+            let target = crate::storage::JsonFile::new(&file);
+            if command == "encrypt" {
+                let key = crate::crypto::Key::new(&new_passphrase()?)?;
+                target.with_key(key).save(&users)?;
+                remove_plain_backups(&file)?;
+                println!("{} is encrypted.", file);
+            } else {
+                target.save(&users)?;
+                println!("{} is no longer encrypted.", file);
+            }
+            return Ok(());
+        }
+        "serve" => {
+            let address = args
+                .option("address")?
+                .unwrap_or("127.0.0.1:8080".to_string());
+            let token = match args.option("token")? {
+                Some(token) => token,
+                None => match std::env::var("DANGI_DONGI_TOKEN") {
+                    Ok(token) => token,
+                    Err(_) => random_token()?,
+                },
+            };
+            args.finish()?;
+            println!("Serving {} on http://{}", file, address);
+            println!("Token: {}", token);
+            return crate::server::serve(storage, users, &address, &token);
+        }
+        "sync" => {
+            let peer = args.positional().ok_or("Missing peer address")?;
+            if peer == "listen" {
+                let address = args
+                    .option("address")?
+                    .unwrap_or_else(crate::sync::default_address);
+                args.finish()?;
+                let secret = match secret {
+                    Some(secret) => secret,
+                    None => random_token()?,
+                };
+                let key = crate::crypto::Key::for_sync(&secret)?;
+                println!("Waiting for peers to sync {} on {}", file, address);
+                println!("Secret: {}", secret);
+                return crate::sync::listen(storage, users, &address, &key);
+            }
+            args.finish()?;
+            let secret = secret.ok_or("Missing --secret, the one the peer listens with")?;
+            let key = crate::crypto::Key::for_sync(&secret)?;
+            let report = crate::sync::connect(&users, &peer, &key)?;
+            println!(
+                "Synced with {}: {} events received, {} sent.",
+                peer, report.received, report.sent
+            );
+            users = report.users;
+        }
+        "member" => {
+            let name = args.positional().ok_or("Missing member name")?;
+            args.finish()?;
+            let detail = users
+                .member_detail(&name)
+                .ok_or(format!("User {} not found", name))?;
+            print!("{}", detail.to_text());
+            return Ok(());
+        }
+        "balances" => {
+            args.finish()?;
+            for line in users.calculate_total_payments()? {
+                println!("{}", line);
+            }
+        }
+        _ => return Err(format!("Unknown command: {}\n\n{}", command, USAGE)),
+    }
+    // Only write when something changed, every save rotates the backups.
+    if crate::format::to_json(&users)? != before || !storage.exists() {
+        storage.save(&users)?;
+    }
+    Ok(())
+}
+
+/// Opens the storage of a group, asking for the passphrase if it is
+/// encrypted.
+fn open_storage(file: &str) -> Result<Box<dyn Storage>, String> {
+    if !crate::storage::is_encrypted(file) {
+        return Ok(crate::storage::open(file));
+    }
+    let passphrase = Zeroizing::new(match std::env::var("DANGI_DONGI_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => rpassword::prompt_password(format!("Passphrase for {}: ", file))
+            .map_err(|e| e.to_string())?,
+    });
+    crate::storage::unlock(file, &passphrase)
+}
+
+fn random_token() -> Result<String, String> {
+    crate::crypto::random_hex(16)
+}
+
+/// Asks for a new passphrase twice.
+fn new_passphrase() -> Result<Zeroizing<String>, String> {
+    if let Ok(passphrase) = std::env::var("DANGI_DONGI_NEW_PASSPHRASE") {
+        return Ok(Zeroizing::new(passphrase));
+    }
+    let passphrase =
+        Zeroizing::new(rpassword::prompt_password("New passphrase: ").map_err(|e| e.to_string())?);
+    let repeated =
+        Zeroizing::new(rpassword::prompt_password("Repeat it: ").map_err(|e| e.to_string())?);
+    if passphrase != repeated {
+        return Err("The passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
+
+/// Backups made before a group was encrypted would still give it away.
+fn remove_plain_backups(file: &str) -> Result<(), String> {
+    for backup in crate::storage::backups(file) {
+        let plain =
+            std::fs::read_to_string(&backup).is_ok_and(|data| !crate::crypto::is_encrypted(&data));
+        if plain {
+            std::fs::remove_file(&backup).map_err(|e| format!("{}: {}", backup.display(), e))?;
+            println!("Removed the unencrypted backup {}.", backup.display());
+        }
+    }
+    Ok(())
+}
+
+fn open(storage: &dyn Storage) -> Result<Users, String> {
+    if !storage.exists() {
+        return Ok(Users::new());
+    }
+    storage.load()
+}
+
+fn add_expense(users: &mut Users, args: &mut Args) -> Result<(), String> {
+    let amount = args.amount("amount")?;
+    let payer = args.option("payer")?;
+
+    if let Some(preset) = args.option("preset")? {
+        args.finish()?;
+        users.record_preset(&preset, amount, payer.as_deref())?;
+        println!("Preset {} recorded.", preset);
+        return Ok(());
+    }
+
+    let payer = payer.ok_or("Missing --payer")?;
+    let amount = amount.ok_or("Missing --amount")?;
+    let description = args.option("description")?.unwrap_or_default();
+    let category = args.option("category")?;
+    let date = args.option("date")?.unwrap_or_else(crate::today);
+    let shares = match args.option("shares")? {
+        Some(s) => parse_participants(&s)?,
+        None => Vec::new(),
+    };
+    args.finish()?;
+
+    let mut builder = TransactionBuilder::new(users, &payer, amount)?
+        .with_description(&description)
+        .with_date(&date)?;
+    if let Some(category) = &category {
+        builder = builder.with_category(category);
+    }
+    for share in &shares {
+        builder = builder.with_weight(&share.name, share.weight)?;
+    }
+    let transaction = builder.build()?;
+    users.add_transaction(transaction)?;
+    println!("{} for user {} added.", amount, payer);
+    Ok(())
+}
+
+fn preset(users: &mut Users, args: &mut Args) -> Result<(), String> {
+    let action = args.positional().ok_or("Missing preset action")?;
+    match action.as_str() {
+        "add" => {
+            let name = args.positional().ok_or("Missing preset name")?;
+            let payer = args.option("payer")?;
+            let participants = match args.option("participants")? {
+                Some(p) => parse_participants(&p)?,
+                None => Vec::new(),
+            };
+            let category = args.option("category")?;
+            let amount = args.amount("amount")?;
+            args.finish()?;
+
+            let split = if participants.iter().all(|p| p.weight == 1) {
+                SplitType::Equal
+            } else {
+                SplitType::UnequalByShare
+            };
+            users.add_preset(
+                Preset::new(&name, split)
+                    .with_payer(payer)
+                    .with_participants(participants)
+                    .with_category(category)
+                    .with_amount(amount),
+            )
+        }
+        "list" => {
+            args.finish()?;
+            for preset in users.presets() {
+                println!(
+                    "{}: payer {}, amount {}, category {}",
+                    preset.name(),
+                    preset.payer().unwrap_or("-"),
+                    preset.amount().map_or("-".to_string(), |a| a.to_string()),
+                    preset.category().unwrap_or("-"),
+                );
+            }
+            Ok(())
+        }
+        "remove" => {
+            let name = args.positional().ok_or("Missing preset name")?;
+            args.finish()?;
+            users.remove_preset(&name)
+        }
+        _ => Err(format!("Unknown preset action: {}", action)),
+    }
+}
+
+fn import(users: &mut Users, args: &mut Args) -> Result<(), String> {
+    let path = args.positional().ok_or("Missing CSV file")?;
+    let profile = args.option("profile")?.ok_or("Missing --profile")?;
+    let payer = args.option("payer")?;
+    args.finish()?;
+
+    let data = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let report = users.import_csv(&profile, &data, payer.as_deref())?;
+    println!(
+        "Imported {} expenses, {} duplicates skipped.",
+        report.imported, report.duplicates
+    );
+    for skipped in report.skipped {
+        println!("Skipped {}", skipped);
+    }
+    Ok(())
+}
+
+/// Creates a new group file from another app's export.
+fn import_app(file: &str, args: &mut Args) -> Result<(), String> {
+    let app = args.positional().ok_or("Missing app name")?;
+    let path = args.positional().ok_or("Missing CSV file")?;
+    args.finish()?;
+    let _lock = GroupLock::acquire(file)?;
+    let storage = crate::storage::open(file);
+    if storage.exists() {
+        return Err(format!(
+            "{} already exists, pick a new file with --file",
+            file
+        ));
+    }
+
+    let data = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let (users, report) = match app.as_str() {
+        "splitwise" => crate::import::from_splitwise(&data)?,
+        "tricount" => crate::import::from_tricount(&data)?,
+        _ => return Err(format!("Unknown app: {}", app)),
+    };
+    println!("Imported {} rows into {}.", report.imported, file);
+    for skipped in report.skipped {
+        println!("Skipped {}", skipped);
+    }
+    for warning in report.warnings {
+        println!("Warning: {}", warning);
+    }
+    storage.save(&users)
+}
+
+/// Writes `contents` to `path`, which may only exist with `force`.
+fn write_output(path: &str, contents: &str, force: bool) -> Result<(), String> {
+    let mut options = std::fs::OpenOptions::new();
+    if force {
+        options.write(true).create(true).truncate(true);
+    } else {
+        options.write(true).create_new(true);
+    }
+    let mut file = options.open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => {
+            format!("{} already exists, add --force to overwrite it", path)
+        }
+        _ => format!("{}: {}", path, e),
+    })?;
+    file.write_all(contents.as_bytes())
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Replays the event log of a group and checks it against the stored state.
+fn verify(file: &str) -> Result<(), String> {
+    let _lock = GroupLock::acquire(file)?;
+    let storage = open_storage(file)?;
+    if !storage.exists() {
+        return Err(format!("{} does not exist", file));
+    }
+    let users = storage.load_stored()?;
+    match users.verify() {
+        Ok(()) => {
+            println!(
+                "{}: the log of {} events replays to the stored state.",
+                file,
+                users.events().len()
+            );
+            Ok(())
+        }
+        Err(errors) => Err(format!(
+            "{}: the log does not match the stored state:\n  - {}",
+            file,
+            errors.join("\n  - ")
+        )),
+    }
+}
+
+/// Three-way merge of two copies of a group, written to `--output` or
+/// over our copy.
+fn merge(args: &mut Args) -> Result<(), String> {
+    let base = args.positional().ok_or("Missing ancestor file")?;
+    let ours = args.positional().ok_or("Missing our file")?;
+    let theirs = args.positional().ok_or("Missing their file")?;
+    let output = args.option("output")?;
+    args.finish()?;
+
+    let _lock = GroupLock::acquire(&ours)?;
+    let load = |file: &str| -> Result<(Box<dyn Storage>, Users), String> {
+        let storage = open_storage(file)?;
+        if !storage.exists() {
+            return Err(format!("{} does not exist", file));
+        }
+        let users = storage.load()?;
+        Ok((storage, users))
+    };
+    let (_, base_users) = load(&base)?;
+    let (our_storage, our_users) = load(&ours)?;
+    let (_, their_users) = load(&theirs)?;
+    let (merged, report) = crate::merge::merge(&base_users, &our_users, &their_users)?;
+
+    match output {
+        Some(output) if output != ours => {
+            let _output_lock = GroupLock::acquire(&output)?;
+            let storage = crate::storage::open(&output);
+            if storage.exists() {
+                return Err(format!("{} already exists", output));
+            }
+            storage.save(&merged)?;
+        }
+        _ => our_storage.save(&merged)?,
+    }
+    println!(
+        "Merged: {} members added, {} removed, {} transactions added, {} edited, {} removed.",
+        report.members_added, report.members_removed, report.added, report.edited, report.removed
+    );
+    if report.conflicts > 0 {
+        println!(
+            "{} conflicting changes kept as ours, resolve them in the TUI.",
+            report.conflicts
+        );
+    }
+    Ok(())
+}
+
+/// Copies a group between storages, e.g. from JSON to SQLite.
+fn migrate_storage(args: &mut Args) -> Result<(), String> {
+    let from = args.positional().ok_or("Missing source file")?;
+    let to = args.positional().ok_or("Missing target file")?;
+    args.finish()?;
+    let _from_lock = GroupLock::acquire(&from)?;
+    let _to_lock = GroupLock::acquire(&to)?;
+    let source = open_storage(&from)?;
+    if !source.exists() {
+        return Err(format!("{} does not exist", from));
+    }
+    crate::storage::migrate(source.as_ref(), crate::storage::open(&to).as_ref())
+        .map_err(|e| format!("{}: {}", to, e))?;
+    println!("Copied {} to {}.", from, to);
+    Ok(())
+}
+
+fn import_profile(users: &mut Users, args: &mut Args) -> Result<(), String> {
+    let action = args.positional().ok_or("Missing import-profile action")?;
+    match action.as_str() {
+        "add" => {
+            let name = args.positional().ok_or("Missing profile name")?;
+            let date = args.option("date")?.ok_or("Missing --date")?;
+            let description = args.option("description")?.ok_or("Missing --description")?;
+            let amount = args.option("amount")?.ok_or("Missing --amount")?;
+            let mut profile = ImportProfile::new(&name, &date, &description, &amount)
+                .with_header(!args.flag("no-header"))
+                .with_negate(args.flag("negate"))
+                .with_decimal_comma(args.flag("decimal-comma"))
+                .with_payer(args.option("payer")?);
+            if let Some(format) = args.option("date-format")? {
+                profile = profile.with_date_format(&format);
+            }
+            if let Some(delimiter) = args.option("delimiter")? {
+                let mut chars = delimiter.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => profile = profile.with_delimiter(c),
+                    _ => return Err(format!("Invalid delimiter: {}", delimiter)),
+                }
+            }
+            args.finish()?;
+            users.add_import_profile(profile)
+        }
+        "rule" => {
+            let name = args.positional().ok_or("Missing profile name")?;
+            let pattern = args.positional().ok_or("Missing pattern")?;
+            let preset = args.positional().ok_or("Missing preset name")?;
+            args.finish()?;
+            users.add_import_rule(&name, &pattern, &preset)
+        }
+        "list" => {
+            args.finish()?;
+            for profile in users.import_profiles() {
+                println!("{}", profile.name());
+                for (pattern, preset) in profile.rules() {
+                    println!("  '{}' -> {}", pattern, preset);
+                }
+            }
+            Ok(())
+        }
+        _ => Err(format!("Unknown import-profile action: {}", action)),
+    }
+}
+
+/// Account names and currency of the ledger and beancount exports.
+fn account(users: &mut Users, args: &mut Args) -> Result<(), String> {
+    let action = args.positional().ok_or("Missing account action")?;
+    match action.as_str() {
+        "set" => {
+            let member = args.positional().ok_or("Missing member name")?;
+            let account = args.positional().ok_or("Missing account name")?;
+            args.finish()?;
+            users.set_account(&member, &account)
+        }
+        "currency" => {
+            let currency = args.positional().ok_or("Missing currency")?;
+            args.finish()?;
+            users.set_currency(&currency)
+        }
+        "list" => {
+            args.finish()?;
+            for (member, account) in users.accounts() {
+                println!("{}: {}", member, account);
+            }
+            Ok(())
+        }
+        _ => Err(format!("Unknown account action: {}", action)),
+    }
+}
+
+/// Budgets of the group, the member and category ones included.
+fn budget(users: &mut Users, args: &mut Args) -> Result<(), String> {
+    let action = args.positional().ok_or("Missing budget action")?;
+    let scope = |args: &mut Args| {
+        let kind = args
+            .positional()
+            .ok_or("Missing budget: group, each, category or member")?;
+        let name = if BudgetScope::takes_name(&kind) {
+            args.positional()
+        } else {
+            None
+        };
+        BudgetScope::parse(&kind, name)
+    };
+    match action.as_str() {
+        "set" => {
+            let scope = scope(args)?;
+            let amount = args.positional().ok_or("Missing amount")?;
+            let amount = amount
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid amount: {}", amount))?;
+            args.finish()?;
+            users.set_budget(scope, amount)
+        }
+        "remove" => {
+            let scope = scope(args)?;
+            args.finish()?;
+            users.remove_budget(&scope)
+        }
+        "alert" => {
+            let percent = args.positional().ok_or("Missing percentage")?;
+            args.finish()?;
+            let percent = percent
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid percentage: {}", percent))?;
+            users.set_budget_alert(percent)
+        }
+        "status" => {
+            args.finish()?;
+            let statuses = users.budget_status();
+            if statuses.is_empty() {
+                println!("No budgets set.");
+            }
+            for status in statuses {
+                let flag = if status.spent > status.limit {
+                    "  OVER BUDGET"
+                } else if status.alert {
+                    "  (alert)"
+                } else {
+                    ""
+                };
+                println!("{}{}", status.summary(), flag);
+            }
+            Ok(())
+        }
+        _ => Err(format!("Unknown budget action: {}", action)),
+    }
+}
+
+/// Parses `alice,bob:2` into participants, the weight defaulting to 1.
+fn parse_participants(list: &str) -> Result<Vec<Participant>, String> {
+    list.split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| match s.split_once(':') {
+            Some((name, weight)) => weight
+                .trim()
+                .parse::<u8>()
+                .map(|w| Participant::new(name.trim(), w))
+                .map_err(|_| format!("Invalid weight: {}", weight)),
+            None => Ok(Participant::new(s.trim(), 1)),
+        })
+        .collect()
+}
@@ -0,0 +1,177 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::XChaCha20Poly1305;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+/// Version of the encrypted envelope written by this build.
+const ENVELOPE_VERSION: u64 = 1;
+
+const SALT_LEN: usize = 16;
+
+const SYNC_SALT: &[u8; SALT_LEN] = b"dangi-dongi sync";
+
+/// An encrypted group file. The group file JSON is encrypted with
+/// XChaCha20-Poly1305 under a key derived from the passphrase with
+/// Argon2id, the KDF parameters are authenticated along with it.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    encrypted: u64,
+    kdf: Kdf,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct Kdf {
+    algorithm: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+/// `len` random bytes from the OS as hex digits. Ids and tokens are drawn
+/// from the same source as the salts and nonces.
+pub fn random_hex(len: usize) -> Result<String, String> {
+    let mut bytes = vec![0u8; len];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| format!("No randomness available: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// A key derived from a passphrase, together with the salt and parameters
+/// it was derived with so files it encrypts can be opened again.
+pub struct Key {
+    kdf: Kdf,
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl Key {
+    /// Derives a key with a fresh salt, for encrypting a file anew.
+    pub fn new(passphrase: &str) -> Result<Key, String> {
+        if passphrase.is_empty() {
+            return Err("The passphrase is empty".to_string());
+        }
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let kdf = Kdf {
+            algorithm: "argon2id".to_string(),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            salt: BASE64.encode(salt),
+        };
+        Key::derive(passphrase, kdf)
+    }
+
+    /// Derives the key peers sync with from their shared secret. The salt
+    /// is fixed so that every peer derives the same key once and for all.
+    pub fn for_sync(secret: &str) -> Result<Key, String> {
+        if secret.is_empty() {
+            return Err("The sync secret is empty".to_string());
+        }
+        let kdf = Kdf {
+            algorithm: "argon2id".to_string(),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            salt: BASE64.encode(SYNC_SALT),
+        };
+        Key::derive(secret, kdf)
+    }
+
+    /// Derives the key of an encrypted file from its passphrase. Whether
+    /// the passphrase is right only shows when decrypting.
+    pub fn for_file(passphrase: &str, data: &str) -> Result<Key, String> {
+        Key::derive(passphrase, envelope(data)?.kdf)
+    }
+
+    fn derive(passphrase: &str, kdf: Kdf) -> Result<Key, String> {
+        if kdf.algorithm != "argon2id" {
+            return Err(format!("Unknown key derivation {}", kdf.algorithm));
+        }
+        let salt = BASE64
+            .decode(&kdf.salt)
+            .map_err(|e| format!("Invalid salt: {}", e))?;
+        let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+            .map_err(|e| format!("Invalid key derivation parameters: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| format!("Key derivation failed: {}", e))?;
+        Ok(Key { kdf, key })
+    }
+
+    /// Encrypts a group file, or a sync message, under a fresh nonce.
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let cipher = XChaCha20Poly1305::new(self.key.as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = serde_json::to_vec(&self.kdf).map_err(|e| e.to_string())?;
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| "Encryption failed".to_string())?;
+        serde_json::to_string_pretty(&Envelope {
+            encrypted: ENVELOPE_VERSION,
+            kdf: self.kdf.clone(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    /// Decrypts an encrypted group file back into its JSON.
+    pub fn decrypt(&self, data: &str) -> Result<String, String> {
+        let envelope = envelope(data)?;
+        if envelope.kdf != self.kdf {
+            return Err("The file was encrypted with another key".to_string());
+        }
+        let nonce = BASE64
+            .decode(&envelope.nonce)
+            .ok()
+            .filter(|n| n.len() == 24)
+            .ok_or("Invalid nonce")?;
+        let ciphertext = BASE64
+            .decode(&envelope.ciphertext)
+            .map_err(|e| format!("Invalid ciphertext: {}", e))?;
+        let aad = serde_json::to_vec(&envelope.kdf).map_err(|e| e.to_string())?;
+        let plaintext = XChaCha20Poly1305::new(self.key.as_ref().into())
+            .decrypt(
+                nonce.as_slice().into(),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| "Wrong passphrase, or the file is damaged".to_string())?;
+        String::from_utf8(plaintext).map_err(|e| e.to_string())
+    }
+}
+
+/// Whether the contents of a group file are encrypted.
+pub fn is_encrypted(data: &str) -> bool {
+    serde_json::from_str::<Value>(data).is_ok_and(|v| v.get("encrypted").is_some())
+}
+
+fn envelope(data: &str) -> Result<Envelope, String> {
+    let envelope: Envelope =
+        serde_json::from_str(data).map_err(|e| format!("Invalid encrypted file: {}", e))?;
+    if envelope.encrypted > ENVELOPE_VERSION {
+        return Err(format!(
+            "Encrypted by a newer version of dangi-dongi (envelope {})",
+            envelope.encrypted
+        ));
+    }
+    Ok(envelope)
+}
@@ -0,0 +1,463 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Transaction, User, Users};
+
+/// Number of events between two snapshots of the state.
+pub const SNAPSHOT_INTERVAL: usize = 100;
+
+/// Something that happened to a group.
+///
+/// The log of events is the group, members, transactions and balances are
+/// only what replaying it gives. Events are appended, never changed, a
+/// sync included: it appends the changes of the peer to the log.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    MemberAdded {
+        name: String,
+    },
+    MemberRemoved {
+        name: String,
+    },
+    ExpenseRecorded {
+        transaction: Transaction,
+    },
+    /// Replaces the transaction at `index` of the state the event applies to.
+    ExpenseEdited {
+        index: usize,
+        transaction: Transaction,
+    },
+    ExpenseRemoved {
+        index: usize,
+    },
+    RepaymentRecorded {
+        transaction: Transaction,
+    },
+    /// Everyone is square, the transactions are cleared.
+    SettledUp,
+}
+
+/// The state after the first `events` events, so that loading a group only
+/// replays the events that came after it.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Snapshot {
+    events: usize,
+    users: Vec<User>,
+    transactions: Vec<Transaction>,
+}
+
+impl Users {
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Applies an event to the state and appends it to the log, taking a
+    /// snapshot every `SNAPSHOT_INTERVAL` events.
+    pub(crate) fn record(&mut self, mut event: Event) -> Result<(), String> {
+        if let Event::ExpenseRecorded { transaction } | Event::RepaymentRecorded { transaction } =
+            &mut event
+            && transaction.id.is_empty()
+        {
+            transaction.id = new_id()?;
+        }
+        self.apply(&event)?;
+        self.events.push(event);
+        if self.events.len().is_multiple_of(SNAPSHOT_INTERVAL) {
+            self.snapshot = Some(Snapshot {
+                events: self.events.len(),
+                users: self.users.clone(),
+                transactions: self.transactions.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Replaces the state with what replaying the log gives, starting from
+    /// the snapshot if there is one.
+    pub fn rebuild(&mut self) -> Result<(), String> {
+        let start = match &self.snapshot {
+            Some(snapshot) if snapshot.events > self.events.len() => {
+                return Err(format!(
+                    "the snapshot is taken after event {}, but the log has only {}",
+                    snapshot.events,
+                    self.events.len()
+                ));
+            }
+            Some(snapshot) => {
+                self.users = snapshot.users.clone();
+                self.transactions = snapshot.transactions.clone();
+                snapshot.events
+            }
+            None => {
+                self.users.clear();
+                self.transactions.clear();
+                0
+            }
+        };
+
+        let events = std::mem::take(&mut self.events);
+        let replayed = events[start..]
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, event)| {
+                self.apply(event)
+                    .map_err(|e| format!("event {}: {}", start + i + 1, e))
+            });
+        self.events = events;
+        replayed
+    }
+
+    /// The group as it was after the first `events` events of its log.
+    pub(crate) fn at(&self, events: usize) -> Result<Users, String> {
+        let mut users = self.clone();
+        users.events.truncate(events);
+        users.snapshot = users.snapshot.filter(|s| s.events <= events);
+        users.conflicts.clear();
+        users.rebuild()?;
+        Ok(users)
+    }
+
+    /// An order-insensitive hash of the state before the first event and
+    /// after each one, so that copies whose logs list the same changes in
+    /// a different order can still tell which states they had in common.
+    pub(crate) fn fingerprints(&self) -> Result<Vec<u64>, String> {
+        let mut replay = Users::new();
+        let mut fingerprint = 0u64;
+        let mut fingerprints = vec![fingerprint];
+        for (i, event) in self.events.iter().enumerate() {
+            fingerprint = fingerprint.wrapping_sub(replay.touched(event));
+            replay
+                .apply(event)
+                .map_err(|e| format!("event {}: {}", i + 1, e))?;
+            if *event == Event::SettledUp {
+                fingerprint = replay
+                    .users
+                    .iter()
+                    .map(member_hash)
+                    .fold(0, u64::wrapping_add);
+            } else if !matches!(event, Event::ExpenseRemoved { .. }) {
+                fingerprint = fingerprint.wrapping_add(replay.touched(event));
+            }
+            fingerprints.push(fingerprint);
+        }
+        Ok(fingerprints)
+    }
+
+    /// The hash of the member or transaction an event changes, 0 if the
+    /// state does not have it.
+    fn touched(&self, event: &Event) -> u64 {
+        match event {
+            Event::MemberAdded { name } | Event::MemberRemoved { name } => self
+                .users
+                .iter()
+                .find(|u| u.name == *name)
+                .map_or(0, member_hash),
+            Event::ExpenseRecorded { transaction } | Event::RepaymentRecorded { transaction } => {
+                self.transactions
+                    .iter()
+                    .find(|t| t.id == transaction.id)
+                    .map_or(0, transaction_hash)
+            }
+            Event::ExpenseEdited { index, .. } | Event::ExpenseRemoved { index } => {
+                self.transactions.get(*index).map_or(0, transaction_hash)
+            }
+            Event::SettledUp => self
+                .users
+                .iter()
+                .map(member_hash)
+                .chain(self.transactions.iter().map(transaction_hash))
+                .fold(0, u64::wrapping_add),
+        }
+    }
+
+    /// Replays the whole log from the start and compares the result with
+    /// the snapshot and the stored state. Returns every difference found.
+    pub fn verify(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let mut replay = Users::new();
+        let snapshot_at = self.snapshot.as_ref().map(|s| s.events);
+        if snapshot_at.is_some_and(|n| n > self.events.len()) {
+            errors.push("the snapshot is taken after the last event".to_string());
+        }
+
+        for i in 0..=self.events.len() {
+            if let Some(snapshot) = &self.snapshot
+                && snapshot.events == i
+            {
+                compare(
+                    "snapshot",
+                    (&replay.users, &replay.transactions),
+                    (&snapshot.users, &snapshot.transactions),
+                    &mut errors,
+                );
+            }
+            if let Some(event) = self.events.get(i)
+                && let Err(e) = replay.apply(event)
+            {
+                errors.push(format!("event {}: {}", i + 1, e));
+                return Err(errors);
+            }
+        }
+        compare(
+            "stored state",
+            (&replay.users, &replay.transactions),
+            (&self.users, &self.transactions),
+            &mut errors,
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// The log of a group stored before there was one: its members joining
+    /// and its transactions being recorded, in order.
+    pub(crate) fn history_from_state(&self) -> Vec<Event> {
+        let members = self.users.iter().map(|u| Event::MemberAdded {
+            name: u.name.clone(),
+        });
+        let transactions = self.transactions.iter().map(|t| {
+            if t.repayment {
+                Event::RepaymentRecorded {
+                    transaction: t.clone(),
+                }
+            } else {
+                Event::ExpenseRecorded {
+                    transaction: t.clone(),
+                }
+            }
+        });
+        members.chain(transactions).collect()
+    }
+
+    /// Gives the transactions of a log from before transactions had an id
+    /// one derived from their place in the log and their contents, so that
+    /// every copy of the group derives the same ids.
+    pub(crate) fn assign_ids(&mut self) {
+        let mut ids: Vec<String> = Vec::new();
+        for (i, event) in self.events.iter_mut().enumerate() {
+            if let Some(snapshot) = &mut self.snapshot
+                && snapshot.events == i
+            {
+                set_ids(&mut snapshot.transactions, &ids);
+            }
+            match event {
+                Event::ExpenseRecorded { transaction }
+                | Event::RepaymentRecorded { transaction } => {
+                    if transaction.id.is_empty() {
+                        transaction.id = legacy_id(i, transaction);
+                    }
+                    ids.push(transaction.id.clone());
+                }
+                Event::ExpenseRemoved { index } if *index < ids.len() => {
+                    ids.remove(*index);
+                }
+                Event::SettledUp => ids.clear(),
+                _ => {}
+            }
+        }
+        if let Some(snapshot) = &mut self.snapshot
+            && snapshot.events == self.events.len()
+        {
+            set_ids(&mut snapshot.transactions, &ids);
+        }
+        set_ids(&mut self.transactions, &ids);
+    }
+
+    fn apply(&mut self, event: &Event) -> Result<(), String> {
+        match event {
+            Event::MemberAdded { name } => {
+                if self.is_member(name) {
+                    return Err(format!("member {} already exists", name));
+                }
+                self.users.push(User {
+                    name: name.clone(),
+                    amount_paid: 0.0,
+                    net_balance: 0.0,
+                });
+            }
+            Event::MemberRemoved { name } => {
+                if !self.is_member(name) {
+                    return Err(format!("{} is not a member", name));
+                }
+                if self
+                    .transactions
+                    .iter()
+                    .any(|t| t.payer == *name || t.participants.iter().any(|p| p.name == *name))
+                {
+                    return Err(format!("member {} still has transactions", name));
+                }
+                self.users.retain(|u| u.name != *name);
+            }
+            Event::ExpenseRecorded { transaction } | Event::RepaymentRecorded { transaction } => {
+                self.check_members(transaction)?;
+                self.book(transaction, 1.0);
+                self.transactions.push(transaction.clone());
+            }
+            Event::ExpenseEdited { index, transaction } => {
+                self.check_members(transaction)?;
+                let old = self
+                    .transactions
+                    .get(*index)
+                    .ok_or(format!("there is no transaction {}", index + 1))?
+                    .clone();
+                self.book(&old, -1.0);
+                self.book(transaction, 1.0);
+                // An edited transaction keeps its identity.
+                self.transactions[*index] = Transaction {
+                    id: old.id,
+                    ..transaction.clone()
+                };
+            }
+            Event::ExpenseRemoved { index } => {
+                let old = self
+                    .transactions
+                    .get(*index)
+                    .ok_or(format!("there is no transaction {}", index + 1))?
+                    .clone();
+                self.book(&old, -1.0);
+                self.transactions.remove(*index);
+            }
+            Event::SettledUp => {
+                for user in &mut self.users {
+                    user.amount_paid = 0.0;
+                    user.net_balance = 0.0;
+                }
+                self.transactions.clear();
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_member(&self, name: &str) -> bool {
+        self.users.iter().any(|u| u.name == name)
+    }
+
+    fn check_members(&self, transaction: &Transaction) -> Result<(), String> {
+        let names = std::iter::once(&transaction.payer)
+            .chain(transaction.participants.iter().map(|p| &p.name));
+        for name in names {
+            if !self.is_member(name) {
+                return Err(format!("{} is not a member", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a transaction to the balances, or takes it off again with a
+    /// `sign` of -1.
+    fn book(&mut self, transaction: &Transaction, sign: f64) {
+        for user in &mut self.users {
+            if user.name == transaction.payer {
+                user.amount_paid += sign * transaction.amount;
+                user.net_balance += sign * transaction.amount;
+            }
+            for p in transaction
+                .participants
+                .iter()
+                .filter(|p| p.name == user.name)
+            {
+                user.net_balance -= sign * p.fair_share.unwrap_or(0.0);
+            }
+        }
+    }
+}
+
+/// A random id for a new transaction.
+fn new_id() -> Result<String, String> {
+    crate::crypto::random_hex(8)
+}
+
+/// The id of a transaction recorded by the `index`th event of a log from
+/// before transactions had ids: its place in the log and a FNV-1a hash of
+/// its contents.
+fn legacy_id(index: usize, transaction: &Transaction) -> String {
+    let json = serde_json::to_string(transaction).unwrap_or_default();
+    format!("{}-{:016x}", index + 1, fnv(json.as_bytes()))
+}
+
+/// FNV-1a hash.
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn member_hash(user: &User) -> u64 {
+    fnv(user.name.as_bytes())
+}
+
+/// Rotated, so that a transaction does not hash like a member.
+fn transaction_hash(transaction: &Transaction) -> u64 {
+    let json = serde_json::to_string(transaction).unwrap_or_default();
+    fnv(json.as_bytes()).rotate_left(1)
+}
+
+/// Fills in missing ids of stored transactions, if they line up with the
+/// transactions the log gives.
+fn set_ids(transactions: &mut [Transaction], ids: &[String]) {
+    if transactions.len() == ids.len() {
+        for (transaction, id) in transactions.iter_mut().zip(ids) {
+            if transaction.id.is_empty() {
+                transaction.id = id.clone();
+            }
+        }
+    }
+}
+
+/// Compares a replayed state with a stored one, member by member and
+/// transaction by transaction.
+fn compare(
+    what: &str,
+    replay: (&[User], &[Transaction]),
+    stored: (&[User], &[Transaction]),
+    errors: &mut Vec<String>,
+) {
+    let eps = 0.005;
+    for user in stored.0 {
+        match replay.0.iter().find(|u| u.name == user.name) {
+            None => errors.push(format!("{}: member {} is not in the log", what, user.name)),
+            Some(replayed) => {
+                if (replayed.amount_paid - user.amount_paid).abs() > eps {
+                    errors.push(format!(
+                        "{}: {} paid {:.2}, the log gives {:.2}",
+                        what, user.name, user.amount_paid, replayed.amount_paid
+                    ));
+                }
+                if (replayed.net_balance - user.net_balance).abs() > eps {
+                    errors.push(format!(
+                        "{}: {} has a balance of {:.2}, the log gives {:.2}",
+                        what, user.name, user.net_balance, replayed.net_balance
+                    ));
+                }
+            }
+        }
+    }
+    for user in replay.0 {
+        if !stored.0.iter().any(|u| u.name == user.name) {
+            errors.push(format!("{}: member {} is missing", what, user.name));
+        }
+    }
+
+    if replay.1.len() != stored.1.len() {
+        errors.push(format!(
+            "{}: {} transactions, the log gives {}",
+            what,
+            stored.1.len(),
+            replay.1.len()
+        ));
+    } else {
+        for (i, (replayed, t)) in replay.1.iter().zip(stored.1).enumerate() {
+            if replayed != t {
+                errors.push(format!(
+                    "{}: transaction {} differs from the log",
+                    what,
+                    i + 1
+                ));
+            }
+        }
+    }
+}
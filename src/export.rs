@@ -0,0 +1,501 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Transaction, Users};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+    Html,
+    Ledger,
+    Beancount,
+}
+
+/// Account names and currency for the plain-text accounting exports.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct AccountingConfig {
+    currency: String,
+    /// Member name and account, members not listed get a default account.
+    accounts: Vec<(String, String)>,
+}
+
+impl Default for AccountingConfig {
+    fn default() -> Self {
+        AccountingConfig {
+            currency: "EUR".to_string(),
+            accounts: Vec::new(),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        ExportFormat::Csv,
+        ExportFormat::Markdown,
+        ExportFormat::Html,
+        ExportFormat::Ledger,
+        ExportFormat::Beancount,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "html" => Some(ExportFormat::Html),
+            "ledger" | "hledger" => Some(ExportFormat::Ledger),
+            "beancount" => Some(ExportFormat::Beancount),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Html => "HTML",
+            ExportFormat::Ledger => "ledger / hledger",
+            ExportFormat::Beancount => "beancount",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Ledger => "ledger",
+            ExportFormat::Beancount => "beancount",
+        }
+    }
+}
+
+/// One balanced entry of the plain-text accounting exports.
+struct JournalEntry {
+    date: String,
+    pending: bool,
+    description: String,
+    category: Option<String>,
+    postings: Vec<(String, f64)>,
+}
+
+/// What a member paid and consumed in expenses, and where they stand
+/// once repayments are counted in.
+struct MemberTotal {
+    name: String,
+    paid: f64,
+    consumed: f64,
+    balance: f64,
+}
+
+impl Users {
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Csv => self.to_csv(),
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Html => self.to_html(),
+            ExportFormat::Ledger => self.to_ledger(),
+            ExportFormat::Beancount => self.to_beancount(),
+        }
+    }
+
+    pub fn set_account(&mut self, member: &str, account: &str) -> Result<(), String> {
+        if !self.users.iter().any(|u| u.name == member) {
+            return Err(format!("User {} not found", member));
+        }
+        let account = account.trim();
+        if account.is_empty() || account.contains(char::is_whitespace) {
+            return Err(format!("Invalid account name: {}", account));
+        }
+        self.accounting.accounts.retain(|(m, _)| m != member);
+        self.accounting
+            .accounts
+            .push((member.to_string(), account.to_string()));
+        Ok(())
+    }
+
+    pub fn set_currency(&mut self, currency: &str) -> Result<(), String> {
+        if currency.is_empty() || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!("Invalid currency: {}", currency));
+        }
+        self.accounting.currency = currency.to_string();
+        Ok(())
+    }
+
+    /// Account of every member, configured or default. Default accounts
+    /// get a number when another member already has the same one.
+    pub fn accounts(&self) -> Vec<(String, String)> {
+        let configured = |name: &str| {
+            self.accounting
+                .accounts
+                .iter()
+                .find(|(m, _)| m == name)
+                .map(|(_, a)| a.clone())
+        };
+        let mut taken: Vec<String> = self
+            .users
+            .iter()
+            .filter_map(|u| configured(&u.name))
+            .collect();
+        self.users
+            .iter()
+            .map(|u| {
+                let account = configured(&u.name).unwrap_or_else(|| {
+                    let base = default_account(&u.name);
+                    let mut account = base.clone();
+                    let mut number = 2;
+                    while taken.contains(&account) {
+                        account = format!("{}{}", base, number);
+                        number += 1;
+                    }
+                    taken.push(account.clone());
+                    account
+                });
+                (u.name.clone(), account)
+            })
+            .collect()
+    }
+
+    /// ledger-cli and hledger journal. A member's account holds their
+    /// balance: what the group owes them.
+    pub fn to_ledger(&self) -> String {
+        let currency = &self.accounting.currency;
+        let mut out = String::new();
+        for entry in self.journal() {
+            out.push_str(&format!(
+                "{} {} {}\n",
+                entry.date.replace('-', "/"),
+                if entry.pending { "!" } else { "*" },
+                entry.description
+            ));
+            if let Some(category) = entry.category {
+                out.push_str(&format!("    ; category: {}\n", category));
+            }
+            for (account, amount) in entry.postings {
+                out.push_str(&format!(
+                    "    {:<40}  {:>10.2} {}\n",
+                    account, amount, currency
+                ));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// beancount ledger, with the same postings as `to_ledger`.
+    pub fn to_beancount(&self) -> String {
+        let currency = &self.accounting.currency;
+        let journal = self.journal();
+        let opening = journal
+            .first()
+            .map_or(crate::today(), |entry| entry.date.clone());
+
+        let mut out = String::new();
+        for (_, account) in self.accounts() {
+            out.push_str(&format!("{} open {} {}\n", opening, account, currency));
+        }
+        out.push('\n');
+        for entry in journal {
+            out.push_str(&format!(
+                "{} {} \"{}\"\n",
+                entry.date,
+                if entry.pending { "!" } else { "*" },
+                entry.description.replace('"', "'")
+            ));
+            if let Some(category) = entry.category {
+                out.push_str(&format!("  category: \"{}\"\n", category.replace('"', "'")));
+            }
+            for (account, amount) in entry.postings {
+                out.push_str(&format!(
+                    "  {:<40}  {:>10.2} {}\n",
+                    account, amount, currency
+                ));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Every transaction as balanced postings: the payer's outlay and the
+    /// share of each participant, followed by the settlement transfers.
+    fn journal(&self) -> Vec<JournalEntry> {
+        let accounts = self.accounts();
+        let account = |name: &str| {
+            accounts
+                .iter()
+                .find(|(m, _)| m == name)
+                .map_or(default_account(name), |(_, a)| a.clone())
+        };
+
+        let mut entries: Vec<JournalEntry> = Vec::new();
+        for t in &self.transactions {
+            let mut postings = vec![(account(&t.payer), cents(t.amount))];
+            for p in &t.participants {
+                let share = cents(p.fair_share.unwrap_or(0.0));
+                if share != 0.0 {
+                    postings.push((account(&p.name), -share));
+                }
+            }
+            // Rounding the shares to cents can leave one over, the largest
+            // share takes it so the entry stays balanced.
+            let rest = cents(postings.iter().map(|(_, a)| a).sum());
+            if let Some(largest) = postings[1..]
+                .iter_mut()
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            {
+                largest.1 = cents(largest.1 - rest);
+            }
+            entries.push(JournalEntry {
+                date: t.date.clone().unwrap_or_else(crate::today),
+                pending: false,
+                description: description(t),
+                category: t.category.clone(),
+                postings,
+            });
+        }
+        // Entries without a date are dated today, keep the journal in order.
+        entries.sort_by(|a, b| a.date.cmp(&b.date));
+
+        for transfer in self.settlement() {
+            let amount = cents(transfer.amount);
+            entries.push(JournalEntry {
+                date: crate::today(),
+                pending: true,
+                description: format!("{} pays {}", transfer.from, transfer.to),
+                category: None,
+                postings: vec![
+                    (account(&transfer.from), amount),
+                    (account(&transfer.to), -amount),
+                ],
+            });
+        }
+        entries
+    }
+
+    /// One row per transaction and participant.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "transaction,date,description,category,kind,payer,amount,participant,weight,share\n",
+        );
+        for (i, t) in self.transactions.iter().enumerate() {
+            for p in &t.participants {
+                let fields = [
+                    (i + 1).to_string(),
+                    t.date.clone().unwrap_or_default(),
+                    t.description.clone(),
+                    t.category.clone().unwrap_or_default(),
+                    kind(t).to_string(),
+                    t.payer.clone(),
+                    format!("{:.2}", t.amount),
+                    p.name.clone(),
+                    p.weight.to_string(),
+                    format!("{:.2}", p.fair_share.unwrap_or(0.0)),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// A summary that can be pasted into a chat.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Dangi-Dongi\n\n## Members\n\n");
+        out.push_str("| Member | Paid | Consumed | Balance |\n|---|---:|---:|---:|\n");
+        for m in self.member_totals() {
+            out.push_str(&format!(
+                "| {} | {:.2} | {:.2} | {:+.2} |\n",
+                markdown_cell(&m.name),
+                m.paid,
+                m.consumed,
+                m.balance
+            ));
+        }
+
+        out.push_str("\n## Expenses\n\n");
+        if self.transactions.is_empty() {
+            out.push_str("No expenses yet.\n");
+        } else {
+            out.push_str("| Date | Description | Category | Paid by | Amount | Shares |\n");
+            out.push_str("|---|---|---|---|---:|---|\n");
+            for t in &self.transactions {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} | {:.2} | {} |\n",
+                    t.date.as_deref().unwrap_or(""),
+                    markdown_cell(&description(t)),
+                    markdown_cell(t.category.as_deref().unwrap_or("")),
+                    markdown_cell(&t.payer),
+                    t.amount,
+                    markdown_cell(&shares(t)),
+                ));
+            }
+        }
+
+        out.push_str("\n## Settlement\n\n");
+        let settlement = self.settlement();
+        if settlement.is_empty() {
+            out.push_str("Everyone is settled up.\n");
+        }
+        for t in settlement {
+            out.push_str(&format!("- {} pays {} {:.2}\n", t.from, t.to, t.amount));
+        }
+        out
+    }
+
+    /// A standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Dangi-Dongi</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; margin-bottom: 1em; }\n\
+             th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }\n\
+             td.number { text-align: right; }\n\
+             .owed { color: #187a2f; }\n\
+             .owes { color: #b3261e; }\n\
+             </style>\n</head>\n<body>\n<h1>Dangi-Dongi</h1>\n",
+        );
+
+        out.push_str("<h2>Members</h2>\n<table>\n<tr><th>Member</th><th>Paid</th><th>Consumed</th><th>Balance</th></tr>\n");
+        for m in self.member_totals() {
+            let class = if m.balance > 0.005 {
+                "owed"
+            } else if m.balance < -0.005 {
+                "owes"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "<tr><td>{}</td><td class=\"number\">{:.2}</td><td class=\"number\">{:.2}</td><td class=\"number {}\">{:+.2}</td></tr>\n",
+                html_escape(&m.name),
+                m.paid,
+                m.consumed,
+                class,
+                m.balance
+            ));
+        }
+        out.push_str("</table>\n");
+
+        out.push_str("<h2>Expenses</h2>\n");
+        if self.transactions.is_empty() {
+            out.push_str("<p>No expenses yet.</p>\n");
+        } else {
+            out.push_str("<table>\n<tr><th>Date</th><th>Description</th><th>Category</th><th>Paid by</th><th>Amount</th><th>Shares</th></tr>\n");
+            for t in &self.transactions {
+                out.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"number\">{:.2}</td><td>{}</td></tr>\n",
+                    html_escape(t.date.as_deref().unwrap_or("")),
+                    html_escape(&description(t)),
+                    html_escape(t.category.as_deref().unwrap_or("")),
+                    html_escape(&t.payer),
+                    t.amount,
+                    html_escape(&shares(t)),
+                ));
+            }
+            out.push_str("</table>\n");
+        }
+
+        out.push_str("<h2>Settlement</h2>\n");
+        let settlement = self.settlement();
+        if settlement.is_empty() {
+            out.push_str("<p>Everyone is settled up.</p>\n");
+        } else {
+            out.push_str("<ul>\n");
+            for t in settlement {
+                out.push_str(&format!(
+                    "<li>{} pays {} {:.2}</li>\n",
+                    html_escape(&t.from),
+                    html_escape(&t.to),
+                    t.amount
+                ));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    fn member_totals(&self) -> Vec<MemberTotal> {
+        let report = crate::reports::Report::new(&self.list_users(), &self.transactions);
+        report
+            .members
+            .into_iter()
+            .zip(self.balances())
+            .map(|(m, (_, balance))| MemberTotal {
+                name: m.name,
+                paid: m.paid,
+                consumed: m.consumed,
+                balance,
+            })
+            .collect()
+    }
+}
+
+fn kind(t: &Transaction) -> &'static str {
+    if t.repayment { "repayment" } else { "expense" }
+}
+
+fn description(t: &Transaction) -> String {
+    if t.repayment {
+        let receiver = t
+            .participants
+            .iter()
+            .find(|p| p.fair_share.unwrap_or(0.0) > 0.0)
+            .map_or("", |p| p.name.as_str());
+        format!("Repayment to {}", receiver)
+    } else {
+        t.description.clone()
+    }
+}
+
+/// Who owes what of a transaction, e.g. `Alice 10.00, Bob 20.00`.
+fn shares(t: &Transaction) -> String {
+    t.participants
+        .iter()
+        .filter(|p| p.fair_share.unwrap_or(0.0) > 0.0)
+        .map(|p| format!("{} {:.2}", p.name, p.fair_share.unwrap_or(0.0)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// `Assets:Receivable:<name>`, keeping only what account names allow.
+fn default_account(name: &str) -> String {
+    let mut account: String = name
+        .split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars().filter(|c| c.is_ascii_alphanumeric());
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + &chars.collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    if !account.starts_with(|c: char| c.is_ascii_uppercase()) {
+        account = format!("M{}", account);
+    }
+    format!("Assets:Receivable:{}", account)
+}
+
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,83 @@
+use crate::Transaction;
+
+/// Which transactions to show, read from a query like
+/// `taxi payer:Ali with:Sara category:food from:2026-01-01 to:2026-01-31`.
+/// Plain words are looked up in the description, case aside. Every part
+/// has to match.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransactionFilter {
+    words: Vec<String>,
+    payer: Option<String>,
+    participants: Vec<String>,
+    category: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl TransactionFilter {
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut filter = TransactionFilter::default();
+        for part in query.split_whitespace() {
+            let Some((key, value)) = part.split_once(':') else {
+                filter.words.push(part.to_lowercase());
+                continue;
+            };
+            if value.is_empty() {
+                return Err(format!("Missing value after {}:", key));
+            }
+            match key {
+                "payer" => filter.payer = Some(value.to_lowercase()),
+                "with" => filter.participants.push(value.to_lowercase()),
+                "category" => filter.category = Some(value.to_lowercase()),
+                "from" | "to" => {
+                    let Some(date) = crate::import::parse_date(value, "%Y-%m-%d") else {
+                        return Err(format!("{} is not a date, expected YYYY-MM-DD", value));
+                    };
+                    if key == "from" {
+                        filter.from = Some(date);
+                    } else {
+                        filter.to = Some(date);
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown filter {}:, use payer:, with:, category:, from: or to:",
+                        key
+                    ));
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, t: &Transaction) -> bool {
+        let description = t.description.to_lowercase();
+        let takes_part = |name: &str| {
+            t.participants.iter().any(|p| {
+                p.name.to_lowercase() == name
+                    && (p.weight > 0 || p.fair_share.unwrap_or(0.0) != 0.0)
+            })
+        };
+        // Transactions without a date fall outside any date range.
+        let date = t.date.as_deref();
+        self.words.iter().all(|w| description.contains(w.as_str()))
+            && self
+                .payer
+                .as_ref()
+                .is_none_or(|p| t.payer.to_lowercase() == *p)
+            && self.participants.iter().all(|p| takes_part(p))
+            && self.category.as_ref().is_none_or(|c| {
+                t.category
+                    .as_ref()
+                    .is_some_and(|tc| tc.to_lowercase() == *c)
+            })
+            && self
+                .from
+                .as_deref()
+                .is_none_or(|from| date.is_some_and(|d| d >= from))
+            && self
+                .to
+                .as_deref()
+                .is_none_or(|to| date.is_some_and(|d| d <= to))
+    }
+}
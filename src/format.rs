@@ -0,0 +1,201 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::Users;
+
+/// Version of the group file format written by this build.
+///
+/// Bump it whenever the format changes in a way older builds would
+/// misread, and add the step from the previous version to `MIGRATIONS`.
+pub const CURRENT_VERSION: u64 = 3;
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// `MIGRATIONS[n]` turns a file of version `n` into version `n + 1`.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [v0_to_v1, v1_to_v2, v2_to_v3];
+
+/// The group file: `Users` with the format version next to it.
+#[derive(Serialize, JsonSchema)]
+#[schemars(title = "Dangi-Dongi group file")]
+struct GroupFile<'a> {
+    version: u64,
+    #[serde(flatten)]
+    users: &'a Users,
+}
+
+/// Files from before the format was versioned have no `version` field and
+/// only `users` and `transactions`, everything added since has defaults.
+fn v0_to_v1(file: &mut Value) -> Result<(), String> {
+    let object = file.as_object_mut().ok_or("expected a JSON object")?;
+    for field in ["users", "transactions"] {
+        if !object.contains_key(field) {
+            return Err(format!("missing field `{}`", field));
+        }
+    }
+    Ok(())
+}
+
+/// Version 2 keeps the group as a log of events. Older files only have the
+/// state, so their log is the members joining and the transactions being
+/// recorded, in order.
+fn v1_to_v2(file: &mut Value) -> Result<(), String> {
+    let object = file.as_object_mut().ok_or("expected a JSON object")?;
+    let list = |field: &str| {
+        object
+            .get(field)
+            .and_then(Value::as_array)
+            .cloned()
+            .ok_or(format!("`{}` is not a list", field))
+    };
+    let mut events = Vec::new();
+    for user in list("users")? {
+        events.push(json!({ "event": "member_added", "name": user["name"] }));
+    }
+    for transaction in list("transactions")? {
+        let event = if transaction["repayment"] == true {
+            "repayment_recorded"
+        } else {
+            "expense_recorded"
+        };
+        events.push(json!({ "event": event, "transaction": transaction }));
+    }
+    object.insert("events".to_string(), Value::Array(events));
+    Ok(())
+}
+
+/// Version 3 gives every transaction an `id`. Ones recorded before get an
+/// id derived from the log when it is read, see `Users::assign_ids`, so
+/// nothing changes in the file itself.
+fn v2_to_v3(_file: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+pub fn to_json(users: &Users) -> Result<String, String> {
+    serde_json::to_string_pretty(&GroupFile {
+        version: CURRENT_VERSION,
+        users,
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Reads a group file of any known version, migrating it to the current
+/// one, replays its event log and validates the result.
+pub fn from_json(data: &str) -> Result<Users, String> {
+    let mut users = read(data)?;
+    users
+        .rebuild()
+        .map_err(|e| format!("invalid event log: {}", e))?;
+    users
+        .validate()
+        .map_err(|errors| format!("invalid group file:\n  - {}", errors.join("\n  - ")))?;
+    Ok(users)
+}
+
+/// Reads a group file as it is stored, without replaying the log.
+pub fn read(data: &str) -> Result<Users, String> {
+    let mut file: Value = serde_json::from_str(data).map_err(|e| format!("invalid JSON: {}", e))?;
+    let version = match file.get("version") {
+        None => 0,
+        Some(v) => v.as_u64().ok_or("`version` is not a number")?,
+    };
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "written by a newer version of dangi-dongi (format {}, this build reads up to {})",
+            version, CURRENT_VERSION
+        ));
+    }
+
+    for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migrate(&mut file).map_err(|e| format!("migrating from format {}: {}", from, e))?;
+    }
+    if let Some(object) = file.as_object_mut() {
+        object.remove("version");
+    }
+
+    let mut users: Users = serde_json::from_value(file).map_err(|e| e.to_string())?;
+    users.assign_ids();
+    Ok(users)
+}
+
+/// Reads and validates an unencrypted group file, errors name the file.
+pub fn load(file_path: &str) -> Result<Users, String> {
+    use crate::storage::Storage;
+    crate::storage::JsonFile::new(file_path).load()
+}
+
+/// JSON Schema of the current group file format.
+pub fn schema() -> String {
+    let schema = schemars::schema_for!(GroupFile);
+    serde_json::to_string_pretty(&schema).unwrap()
+}
+
+impl Users {
+    /// Checks what serde cannot: that every name refers to a member and
+    /// that the amounts make sense. Returns every problem found.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let is_member = |name: &str| self.users.iter().any(|u| u.name == name);
+
+        for (i, user) in self.users.iter().enumerate() {
+            if self.users[..i].iter().any(|u| u.name == user.name) {
+                errors.push(format!("member {} is listed twice", user.name));
+            }
+        }
+
+        for (i, t) in self.transactions.iter().enumerate() {
+            let mut at = format!("transaction {}", i + 1);
+            if !t.description.is_empty() {
+                at.push_str(&format!(" ({})", t.description));
+            }
+            // Refunds have a negative amount, and negative shares with it.
+            if let Err(e) = crate::check_amount(t.amount) {
+                errors.push(format!("{}: {}", at, e));
+            }
+            if !is_member(&t.payer) {
+                errors.push(format!("{}: payer {} is not a member", at, t.payer));
+            }
+            let mut total = 0.0;
+            for p in &t.participants {
+                if !is_member(&p.name) {
+                    errors.push(format!("{}: participant {} is not a member", at, p.name));
+                }
+                match p.fair_share {
+                    Some(share) if !share.is_finite() || share * t.amount < 0.0 => errors.push(
+                        format!("{}: share {} of {} has the wrong sign", at, share, p.name),
+                    ),
+                    Some(share) => total += share,
+                    None => errors.push(format!("{}: share of {} is missing", at, p.name)),
+                }
+            }
+            if (total - t.amount).abs() > 0.01 {
+                errors.push(format!(
+                    "{}: shares add up to {:.2} instead of {:.2}",
+                    at, total, t.amount
+                ));
+            }
+        }
+
+        for preset in &self.presets {
+            let names = preset
+                .payer
+                .iter()
+                .chain(preset.participants.iter().map(|p| &p.name));
+            for name in names {
+                if !is_member(name) {
+                    errors.push(format!("preset {}: {} is not a member", preset.name, name));
+                }
+            }
+            if let Some(Err(e)) = preset.amount.map(crate::check_amount) {
+                errors.push(format!("preset {}: {}", preset.name, e));
+            }
+        }
+        errors.extend(self.budgets.errors());
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
@@ -0,0 +1,707 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Participant, Transaction, Users};
+
+/// Describes how the CSV export of one bank or card maps onto expenses.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct ImportProfile {
+    name: String,
+    /// Columns are given by header name, or by position starting at 0.
+    date_column: String,
+    description_column: String,
+    amount_column: String,
+    /// Built from `%d`, `%m` and `%Y`, e.g. `%d.%m.%Y`.
+    date_format: String,
+    delimiter: char,
+    has_header: bool,
+    /// Most statements list spending as negative amounts.
+    negate: bool,
+    /// Amounts are written like `1.234,56`.
+    decimal_comma: bool,
+    /// Payer of the rows that no rule gives one to.
+    payer: Option<String>,
+    rules: Vec<ImportRule>,
+}
+
+/// Rows whose description contains `pattern` are recorded with `preset`.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct ImportRule {
+    pattern: String,
+    preset: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub duplicates: usize,
+    /// Rows that were left out, with the reason.
+    pub skipped: Vec<String>,
+    /// Rows that could not be imported exactly, and balance mismatches.
+    pub warnings: Vec<String>,
+}
+
+impl ImportProfile {
+    pub fn new(
+        name: &str,
+        date_column: &str,
+        description_column: &str,
+        amount_column: &str,
+    ) -> Self {
+        ImportProfile {
+            name: name.to_string(),
+            date_column: date_column.to_string(),
+            description_column: description_column.to_string(),
+            amount_column: amount_column.to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+            delimiter: ',',
+            has_header: true,
+            negate: false,
+            decimal_comma: false,
+            payer: None,
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn with_date_format(mut self, date_format: &str) -> Self {
+        self.date_format = date_format.to_string();
+        self
+    }
+
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    pub fn with_negate(mut self, negate: bool) -> Self {
+        self.negate = negate;
+        self
+    }
+
+    pub fn with_decimal_comma(mut self, decimal_comma: bool) -> Self {
+        self.decimal_comma = decimal_comma;
+        self
+    }
+
+    pub fn with_payer(mut self, payer: Option<String>) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rules(&self) -> Vec<(&str, &str)> {
+        self.rules
+            .iter()
+            .map(|r| (r.pattern.as_str(), r.preset.as_str()))
+            .collect()
+    }
+
+    fn column(&self, header: Option<&[String]>, column: &str) -> Result<usize, String> {
+        if let Ok(i) = column.parse::<usize>() {
+            return Ok(i);
+        }
+        header
+            .and_then(|h| h.iter().position(|c| c.trim() == column))
+            .ok_or(format!("Column {} not found", column))
+    }
+
+    fn parse_amount(&self, value: &str) -> Option<f64> {
+        let mut value: String = value
+            .chars()
+            .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-'))
+            .collect();
+        if self.decimal_comma {
+            value = value.replace('.', "").replace(',', ".");
+        } else {
+            value = value.replace(',', "");
+        }
+        let amount = value.parse::<f64>().ok()?;
+        Some(if self.negate { -amount } else { amount })
+    }
+}
+
+impl Users {
+    pub fn add_import_profile(&mut self, profile: ImportProfile) -> Result<(), String> {
+        if let Some(payer) = &profile.payer
+            && !self.users.iter().any(|u| &u.name == payer)
+        {
+            return Err(format!("User {} not found", payer));
+        }
+        self.import_profiles.retain(|p| p.name != profile.name);
+        self.import_profiles.push(profile);
+        Ok(())
+    }
+
+    pub fn import_profiles(&self) -> &[ImportProfile] {
+        &self.import_profiles
+    }
+
+    /// Adds a rule to a profile, rules are tried in the order they were added.
+    pub fn add_import_rule(
+        &mut self,
+        profile: &str,
+        pattern: &str,
+        preset: &str,
+    ) -> Result<(), String> {
+        if self.find_preset(preset).is_none() {
+            return Err(format!("Preset {} does not exist", preset));
+        }
+        let profile = self
+            .import_profiles
+            .iter_mut()
+            .find(|p| p.name == profile)
+            .ok_or(format!("Import profile {} does not exist", profile))?;
+        profile.rules.push(ImportRule {
+            pattern: pattern.to_string(),
+            preset: preset.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Imports the rows of a CSV statement through a saved profile.
+    ///
+    /// Rows that were in the ledger before the import (same date,
+    /// description and amount) are skipped. The first matching rule decides payer, split and category,
+    /// other rows are split equally and paid by `payer` or the profile's payer.
+    pub fn import_csv(
+        &mut self,
+        profile: &str,
+        data: &str,
+        payer: Option<&str>,
+    ) -> Result<ImportReport, String> {
+        let profile = self
+            .import_profiles
+            .iter()
+            .find(|p| p.name == profile)
+            .ok_or(format!("Import profile {} does not exist", profile))?
+            .clone();
+
+        let mut rows = parse_csv(data, profile.delimiter);
+        let header = if profile.has_header && !rows.is_empty() {
+            Some(rows.remove(0))
+        } else {
+            None
+        };
+        let date_idx = profile.column(header.as_deref(), &profile.date_column)?;
+        let description_idx = profile.column(header.as_deref(), &profile.description_column)?;
+        let amount_idx = profile.column(header.as_deref(), &profile.amount_column)?;
+        let first_row = if header.is_some() { 2 } else { 1 };
+
+        // Two equal rows in one statement are two expenses, so only what
+        // was there before the import, settled or not, counts as a
+        // duplicate.
+        let existing = self.all_transactions();
+        let mut report = ImportReport::default();
+        for (i, row) in rows.iter().enumerate() {
+            let line = i + first_row;
+            let field = |idx: usize| row.get(idx).map(|v| v.trim()).unwrap_or("");
+
+            let Some(date) = parse_date(field(date_idx), &profile.date_format) else {
+                report
+                    .skipped
+                    .push(format!("row {}: invalid date '{}'", line, field(date_idx)));
+                continue;
+            };
+            let description = field(description_idx).to_string();
+            // Money coming back is recorded as a refund.
+            let amount = match profile.parse_amount(field(amount_idx)) {
+                Some(a) => a,
+                None => {
+                    report.skipped.push(format!(
+                        "row {}: invalid amount '{}'",
+                        line,
+                        field(amount_idx)
+                    ));
+                    continue;
+                }
+            };
+
+            if existing.iter().any(|t| {
+                t.date.as_deref() == Some(date.as_str())
+                    && t.description == description
+                    && (t.amount - amount).abs() < 1e-9
+            }) {
+                report.duplicates += 1;
+                continue;
+            }
+
+            let rule = profile.rules.iter().find(|r| {
+                description
+                    .to_lowercase()
+                    .contains(&r.pattern.to_lowercase())
+            });
+            let transaction = match rule {
+                Some(rule) => {
+                    let rule_payer = payer
+                        .map(|p| p.to_string())
+                        .or(self
+                            .find_preset(&rule.preset)
+                            .and_then(|p| p.payer().map(|p| p.to_string())))
+                        .or(profile.payer.clone());
+                    self.preset_transaction(&rule.preset, Some(amount), rule_payer.as_deref())
+                }
+                None => match payer.map(|p| p.to_string()).or(profile.payer.clone()) {
+                    Some(payer) if self.users.iter().any(|u| u.name == payer) => {
+                        let participants = self
+                            .users
+                            .iter()
+                            .map(|u| Participant::new(&u.name, 1))
+                            .collect();
+                        Ok(Transaction::new(amount, payer, participants))
+                    }
+                    Some(payer) => Err(format!("User {} not found", payer)),
+                    None => Err("no payer given".to_string()),
+                },
+            };
+            match transaction {
+                Ok(transaction) => {
+                    let transaction = transaction
+                        .with_description(&description)
+                        .with_date(Some(date));
+                    match self.add_transaction(transaction) {
+                        Ok(()) => report.imported += 1,
+                        Err(e) => report.skipped.push(format!("row {}: {}", line, e)),
+                    }
+                }
+                Err(e) => report.skipped.push(format!("row {}: {}", line, e)),
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Splits CSV data into rows of fields, handling quoted fields.
+pub(crate) fn parse_csv(data: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = data.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            row.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            row.push(std::mem::take(&mut field));
+            if row.iter().any(|f| !f.trim().is_empty()) {
+                rows.push(std::mem::take(&mut row));
+            } else {
+                row.clear();
+            }
+        } else {
+            field.push(c);
+        }
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.trim().is_empty()) {
+        rows.push(row);
+    }
+    rows
+}
+
+/// Reads a date written in `format` and returns it as `YYYY-MM-DD`.
+pub(crate) fn parse_date(value: &str, format: &str) -> Option<String> {
+    let (mut day, mut month, mut year) = (None, None, None);
+    let mut value = value.trim();
+    let mut format = format.chars();
+
+    while let Some(f) = format.next() {
+        if f == '%' {
+            let token = format.next()?;
+            let max_len = if token == 'Y' { 4 } else { 2 };
+            let len = value
+                .chars()
+                .take(max_len)
+                .take_while(|c| c.is_ascii_digit())
+                .count();
+            let number = value[..len].parse::<u32>().ok()?;
+            value = &value[len..];
+            match token {
+                'd' => day = Some(number),
+                'm' => month = Some(number),
+                'Y' => year = Some(number),
+                _ => return None,
+            }
+        } else {
+            value = value.strip_prefix(f)?;
+        }
+    }
+
+    let (day, month, year) = (day?, month?, year?);
+    if !value.is_empty() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// Builds a new group from a Splitwise CSV export.
+///
+/// Every member column holds what the row did to that member's balance, so
+/// the paid amount and the shares are worked back from it. The closing
+/// "Total balance" row is checked against the imported balances.
+pub fn from_splitwise(data: &str) -> Result<(Users, ImportReport), String> {
+    let mut rows = parse_csv(data, ',');
+    if rows.is_empty() {
+        return Err("The file is empty".to_string());
+    }
+    let header = rows.remove(0);
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or(format!("Column {} not found", name))
+    };
+    let (date_idx, description_idx, category_idx) =
+        (column("Date")?, column("Description")?, column("Category")?);
+    let (cost_idx, currency_idx) = (column("Cost")?, column("Currency")?);
+    let first_member = currency_idx + 1;
+    let members: Vec<String> = header[first_member..]
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+
+    let mut app = AppImport::new(&members)?;
+    let mut currencies: Vec<String> = Vec::new();
+    let mut expected = None;
+    for (i, row) in rows.iter().enumerate() {
+        let line = i + 2;
+        let field = |idx: usize| row.get(idx).map(|v| v.trim()).unwrap_or("");
+        let nets: Vec<f64> = (0..members.len())
+            .map(|m| field(first_member + m).parse::<f64>().unwrap_or(0.0))
+            .collect();
+
+        if field(description_idx) == "Total balance" {
+            expected = Some(nets);
+            continue;
+        }
+        let Ok(cost) = field(cost_idx).parse::<f64>() else {
+            app.report
+                .skipped
+                .push(format!("row {}: invalid cost '{}'", line, field(cost_idx)));
+            continue;
+        };
+        let currency = field(currency_idx).to_string();
+        if !currencies.contains(&currency) {
+            currencies.push(currency);
+        }
+
+        let row = AppRow {
+            line,
+            date: parse_date(field(date_idx), "%Y-%m-%d"),
+            description: field(description_idx).to_string(),
+            category: Some(field(category_idx).to_string()).filter(|c| !c.is_empty()),
+            transfer: field(category_idx) == "Payment",
+        };
+        let payers: Vec<usize> = (0..nets.len()).filter(|&m| nets[m] > EPS).collect();
+        if let [payer] = payers[..] {
+            let shares: Vec<f64> = (0..nets.len())
+                .map(|m| if m == payer { cost - nets[m] } else { -nets[m] })
+                .collect();
+            if shares.iter().all(|s| *s > -EPS) {
+                app.record(&row, payer, cost, &shares);
+                continue;
+            }
+        }
+        app.record_nets(&row, &nets);
+    }
+
+    if currencies.len() > 1 {
+        app.report.warnings.push(format!(
+            "Several currencies ({}) were imported as one, without converting",
+            currencies.join(", ")
+        ));
+    }
+    app.finish(expected)
+}
+
+/// Builds a new group from a Tricount CSV export.
+///
+/// Rows are paid by the "Paid by" member, or by the "Paid by <name>" columns
+/// when several paid, and shared by the "Impacted to <name>" columns. Money
+/// transfers become repayments. A "Balance" row gives each member's balance
+/// in the "Impacted to <name>" columns, which the import is checked against.
+pub fn from_tricount(data: &str) -> Result<(Users, ImportReport), String> {
+    let mut rows = parse_csv(data, ',');
+    if rows.is_empty() {
+        return Err("The file is empty".to_string());
+    }
+    let header: Vec<String> = rows
+        .remove(0)
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+    let find = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.iter().any(|n| h.eq_ignore_ascii_case(n)))
+    };
+    let title_idx = find(&["Title", "Description"]).ok_or("Column Title not found")?;
+    let amount_idx =
+        find(&["Amount in default currency", "Amount"]).ok_or("Column Amount not found")?;
+    let payer_idx = find(&["Paid by"]).ok_or("Column Paid by not found")?;
+    let date_idx = header.iter().position(|h| h.starts_with("Date"));
+    let type_idx = find(&["Transaction type", "Type"]);
+    let category_idx = find(&["Category"]);
+
+    let member_columns = |prefix: &str| -> Vec<(String, usize)> {
+        header
+            .iter()
+            .enumerate()
+            .filter_map(|(i, h)| {
+                let name = h.strip_prefix(prefix)?.trim_start_matches(':').trim();
+                Some((name.to_string(), i)).filter(|(n, _)| !n.is_empty())
+            })
+            .collect()
+    };
+    let impacted = member_columns("Impacted to");
+    let paid = member_columns("Paid by ");
+    if impacted.is_empty() {
+        return Err("No 'Impacted to' columns found".to_string());
+    }
+    let members: Vec<String> = impacted.iter().map(|(n, _)| n.clone()).collect();
+
+    let mut app = AppImport::new(&members)?;
+    let mut expected = None;
+    for (i, row) in rows.iter().enumerate() {
+        let line = i + 2;
+        let field = |idx: usize| row.get(idx).map(|v| v.trim()).unwrap_or("");
+        let number = |idx: usize| field(idx).parse::<f64>().map(f64::abs);
+
+        if ["Balance", "Balances", "Total balance"]
+            .iter()
+            .any(|b| field(title_idx).eq_ignore_ascii_case(b))
+        {
+            expected = Some(
+                impacted
+                    .iter()
+                    .map(|(_, idx)| field(*idx).parse::<f64>().unwrap_or(0.0))
+                    .collect(),
+            );
+            continue;
+        }
+        let Ok(cost) = number(amount_idx) else {
+            app.report.skipped.push(format!(
+                "row {}: invalid amount '{}'",
+                line,
+                field(amount_idx)
+            ));
+            continue;
+        };
+        let kind = type_idx.map(field).unwrap_or("").to_lowercase();
+        if kind == "income" {
+            app.report.skipped.push(format!(
+                "row {}: income '{}' has no equivalent here",
+                line,
+                field(title_idx)
+            ));
+            continue;
+        }
+
+        let shares: Vec<f64> = impacted
+            .iter()
+            .map(|(_, idx)| number(*idx).unwrap_or(0.0))
+            .collect();
+        let paid: Vec<f64> = match paid.len() {
+            0 => Vec::new(),
+            _ => members
+                .iter()
+                .map(|m| {
+                    paid.iter()
+                        .find(|(n, _)| n == m)
+                        .map_or(0.0, |(_, idx)| number(*idx).unwrap_or(0.0))
+                })
+                .collect(),
+        };
+        let row = AppRow {
+            line,
+            date: date_idx.and_then(|idx| {
+                let date = field(idx).get(..10).unwrap_or("");
+                parse_date(date, "%Y-%m-%d").or(parse_date(date, "%d/%m/%Y"))
+            }),
+            description: field(title_idx).to_string(),
+            category: category_idx
+                .map(field)
+                .map(|c| c.to_string())
+                .filter(|c| !c.is_empty()),
+            transfer: kind.contains("transfer"),
+        };
+
+        if paid.iter().filter(|p| **p > EPS).count() > 1 {
+            let nets: Vec<f64> = (0..members.len()).map(|m| paid[m] - shares[m]).collect();
+            app.record_nets(&row, &nets);
+            continue;
+        }
+        match members.iter().position(|m| m == field(payer_idx)) {
+            Some(payer) => app.record(&row, payer, cost, &shares),
+            None => app.report.skipped.push(format!(
+                "row {}: unknown payer '{}'",
+                line,
+                field(payer_idx)
+            )),
+        }
+    }
+    app.finish(expected)
+}
+
+const EPS: f64 = 0.005;
+
+/// One row of another app's export, after its columns have been read.
+#[derive(Clone)]
+struct AppRow {
+    line: usize,
+    date: Option<String>,
+    description: String,
+    category: Option<String>,
+    transfer: bool,
+}
+
+/// Collects the rows of another app's export into a new group.
+struct AppImport {
+    users: Users,
+    members: Vec<String>,
+    report: ImportReport,
+}
+
+impl AppImport {
+    fn new(members: &[String]) -> Result<Self, String> {
+        let mut users = Users::new();
+        for member in members {
+            users.add_member(member)?;
+        }
+        Ok(AppImport {
+            users,
+            members: members.to_vec(),
+            report: ImportReport::default(),
+        })
+    }
+
+    /// Records `cost` paid by `payer` and split by amount into `shares`.
+    fn record(&mut self, row: &AppRow, payer: usize, cost: f64, shares: &[f64]) {
+        if self.record_expense(row, payer, cost, shares) {
+            self.report.imported += 1;
+        }
+    }
+
+    fn record_expense(&mut self, row: &AppRow, payer: usize, cost: f64, shares: &[f64]) -> bool {
+        let total: f64 = shares.iter().sum();
+        if (total - cost).abs() > EPS {
+            self.report.skipped.push(format!(
+                "row {}: shares ({:.2}) do not add up to the amount ({:.2})",
+                row.line, total, cost
+            ));
+            return false;
+        }
+        if cost <= EPS {
+            self.report
+                .skipped
+                .push(format!("row {}: nothing to record", row.line));
+            return false;
+        }
+
+        let receivers: Vec<usize> = (0..shares.len()).filter(|&m| shares[m] > EPS).collect();
+        let recorded = if row.transfer
+            && let [receiver] = receivers[..]
+            && receiver != payer
+        {
+            self.users.record_repayment(
+                &self.members[payer],
+                &self.members[receiver],
+                cost,
+                row.date.clone(),
+            )
+        } else {
+            let participants = self
+                .members
+                .iter()
+                .zip(shares)
+                .map(|(m, s)| Participant::with_share(m, *s))
+                .collect();
+            let transaction = Transaction::new(cost, self.members[payer].clone(), participants)
+                .with_description(&row.description)
+                .with_category(row.category.clone())
+                .with_date(row.date.clone());
+            self.users.add_transaction(transaction)
+        };
+        match recorded {
+            Ok(()) => true,
+            Err(e) => {
+                self.report.skipped.push(format!("row {}: {}", row.line, e));
+                false
+            }
+        }
+    }
+
+    /// Records a row that only gives the change of each balance, as one
+    /// expense per member who came out ahead, shared among the ones behind.
+    fn record_nets(&mut self, row: &AppRow, nets: &[f64]) {
+        let owed: f64 = nets.iter().filter(|n| **n < -EPS).map(|n| -n).sum();
+        if owed <= EPS {
+            self.report
+                .skipped
+                .push(format!("row {}: no effect on balances", row.line));
+            return;
+        }
+        self.report.warnings.push(format!(
+            "row {}: '{}' had several payers, recorded as one expense per payer",
+            row.line, row.description
+        ));
+        let row = AppRow {
+            transfer: false,
+            ..row.clone()
+        };
+        let mut recorded = false;
+        for (payer, net) in nets.iter().enumerate().filter(|(_, n)| **n > EPS) {
+            let shares: Vec<f64> = nets
+                .iter()
+                .map(|n| if *n < -EPS { net * -n / owed } else { 0.0 })
+                .collect();
+            recorded |= self.record_expense(&row, payer, *net, &shares);
+        }
+        if recorded {
+            self.report.imported += 1;
+        }
+    }
+
+    /// Compares the imported balances with the ones the export gives.
+    fn finish(mut self, expected: Option<Vec<f64>>) -> Result<(Users, ImportReport), String> {
+        let Some(expected) = expected else {
+            self.report
+                .warnings
+                .push("The export has no balances to check the import against".to_string());
+            return Ok((self.users, self.report));
+        };
+        for ((name, balance), expected) in self.users.balances().iter().zip(expected) {
+            if (balance - expected).abs() > 0.01 {
+                self.report.warnings.push(format!(
+                    "Balance of {} is {:.2}, the source app had {:.2}",
+                    name, balance, expected
+                ));
+            }
+        }
+        Ok((self.users, self.report))
+    }
+}
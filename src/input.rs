@@ -0,0 +1,167 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What a text field has to hold before it is accepted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Validator {
+    /// Anything but nothing.
+    Text,
+    /// A member name.
+    Name,
+    /// A non-zero amount, negative for refunds.
+    Amount,
+    /// A day as `YYYY-MM-DD`.
+    Date,
+    /// A transaction filter, empty for none.
+    Filter,
+}
+
+impl Validator {
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match self {
+            Validator::Filter => crate::filter::TransactionFilter::parse(value).map(|_| ()),
+            _ if value.is_empty() => Err("Nothing entered".to_string()),
+            Validator::Text => Ok(()),
+            Validator::Name if value.graphemes(true).count() > 32 => {
+                Err("Names are at most 32 characters long".to_string())
+            }
+            Validator::Name => Ok(()),
+            Validator::Amount => match value.parse::<f64>() {
+                Ok(0.0) => Err("The amount cannot be zero".to_string()),
+                Ok(amount) => crate::check_amount(amount),
+                Err(_) => Err(format!("{} is not an amount", value)),
+            },
+            Validator::Date => match crate::import::parse_date(value, "%Y-%m-%d") {
+                Some(_) => Ok(()),
+                None => Err(format!("{} is not a date, expected YYYY-MM-DD", value)),
+            },
+        }
+    }
+}
+
+/// A one line text field with a cursor. The cursor moves and deletes by
+/// grapheme, so accents and emoji go as a whole.
+pub struct TextInput {
+    value: String,
+    /// Byte offset into `value`, always on a grapheme boundary.
+    cursor: usize,
+    validator: Validator,
+    /// Why the value was not accepted, until it is edited.
+    error: Option<String>,
+}
+
+impl TextInput {
+    pub fn new(validator: Validator) -> Self {
+        TextInput {
+            value: String::new(),
+            cursor: 0,
+            validator,
+            error: None,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Replaces the value, the cursor going to its end.
+    pub fn set(&mut self, value: &str) {
+        self.value = value.to_string();
+        self.cursor = self.value.len();
+        self.error = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set("");
+    }
+
+    /// Inserts at the cursor. Line breaks and other control characters,
+    /// as pasted text may have, are left out.
+    pub fn insert(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        self.value.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+        self.error = None;
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(start) = self.previous_boundary() {
+            self.value.replace_range(start..self.cursor, "");
+            self.cursor = start;
+            self.error = None;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if let Some(end) = self.next_boundary() {
+            self.value.replace_range(self.cursor..end, "");
+            self.error = None;
+        }
+    }
+
+    fn previous_boundary(&self) -> Option<usize> {
+        self.value[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.value[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|g| self.cursor + g.len())
+    }
+
+    /// Edits the field with a key: characters, Backspace, Delete, the
+    /// arrows, Home and End. Returns whether the key was used.
+    pub fn handle_key(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.insert(c.encode_utf8(&mut [0; 4]))
+            }
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.cursor = self.previous_boundary().unwrap_or(0),
+            KeyCode::Right => self.cursor = self.next_boundary().unwrap_or(self.cursor),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Checks the value, keeping the error to show if it is not valid.
+    pub fn validate(&mut self) -> bool {
+        self.error = self.validator.check(&self.value).err();
+        self.error.is_none()
+    }
+
+    /// Shows an error found outside the validator, e.g. a taken name.
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    /// The field after `prompt`, the cursor shown reversed, and the error
+    /// under it in `error_style`.
+    pub fn lines(&self, prompt: &str, error_style: Style) -> Vec<Line<'static>> {
+        let (before, rest) = self.value.split_at(self.cursor);
+        let (under, after) = match rest.graphemes(true).next() {
+            Some(g) => (g.to_string(), rest[g.len()..].to_string()),
+            None => (" ".to_string(), String::new()),
+        };
+        let mut lines = vec![Line::from(vec![
+            Span::raw(prompt.to_string()),
+            Span::raw(before.to_string()),
+            Span::styled(under, Style::default().add_modifier(Modifier::REVERSED)),
+            Span::raw(after),
+        ])];
+        if let Some(error) = &self.error {
+            lines.push(Line::from(Span::styled(error.clone(), error_style)));
+        }
+        lines
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Something the TUI does on a key press.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    AddUser,
+    Remove,
+    AddTransaction,
+    UsePreset,
+    SettleUp,
+    Export,
+    ResolveConflicts,
+    Sync,
+    Stats,
+    Charts,
+    Filter,
+    Sort,
+    ReverseSort,
+    Member,
+    NextPanel,
+    Help,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::AddUser,
+        Action::Remove,
+        Action::AddTransaction,
+        Action::UsePreset,
+        Action::SettleUp,
+        Action::Export,
+        Action::ResolveConflicts,
+        Action::Sync,
+        Action::Stats,
+        Action::Charts,
+        Action::Filter,
+        Action::Sort,
+        Action::ReverseSort,
+        Action::Member,
+        Action::NextPanel,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::AddUser => "add a user",
+            Action::Remove => "remove a user or a transaction",
+            Action::AddTransaction => "add a transaction",
+            Action::UsePreset => "add a transaction from a preset",
+            Action::SettleUp => "settle up",
+            Action::Export => "export the ledger",
+            Action::ResolveConflicts => "resolve merge conflicts",
+            Action::Sync => "sync with the peer now",
+            Action::Stats => "show or hide the stats",
+            Action::Charts => "show or hide the charts",
+            Action::Filter => "filter the transactions",
+            Action::Sort => "sort the transactions by the next column",
+            Action::ReverseSort => "reverse the sort order",
+            Action::Member => "show a member's details",
+            Action::NextPanel => "show the next panel",
+            Action::Help => "show this help",
+            Action::Quit => "quit",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::AddUser => &["u"],
+            Action::Remove => &["r"],
+            Action::AddTransaction => &["t"],
+            Action::UsePreset => &["p"],
+            Action::SettleUp => &["s"],
+            Action::Export => &["e"],
+            Action::ResolveConflicts => &["c"],
+            Action::Sync => &["y"],
+            Action::Stats => &["i"],
+            Action::Charts => &["g"],
+            Action::Filter => &["/"],
+            Action::Sort => &["o"],
+            Action::ReverseSort => &["O"],
+            Action::Member => &["m"],
+            Action::NextPanel => &["tab"],
+            Action::Help => &["?", "f1"],
+            Action::Quit => &["q"],
+        }
+    }
+}
+
+/// A key as written in the config file: a character, a named key like
+/// `f1` or `tab`, or either with `ctrl+` in front.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key {
+    code: KeyCode,
+    ctrl: bool,
+}
+
+impl Key {
+    pub fn parse(name: &str) -> Result<Key, String> {
+        let (ctrl, rest) = match name.to_lowercase().strip_prefix("ctrl+") {
+            Some(_) => (true, &name[5..]),
+            None => (false, name),
+        };
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                // These work the inputs and scroll the lists.
+                "enter" | "esc" | "up" | "down" | "left" | "right" | "backspace" | "home"
+                | "end" | "pageup" | "pagedown" => {
+                    return Err(format!(
+                        "{} cannot be bound, it is used by the inputs",
+                        rest
+                    ));
+                }
+                other => match other.strip_prefix('f').map(str::parse::<u8>) {
+                    Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("Unknown key: {}", name)),
+                },
+            },
+        };
+        Ok(Key { code, ctrl })
+    }
+
+    /// Whether the key types a character into a text field.
+    pub fn is_text(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && !self.ctrl
+    }
+
+    /// Whether `event` is this key with no other modifiers held, Shift
+    /// aside for characters since it is how many of them are typed.
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let mut modifiers = event.modifiers;
+        if matches!(event.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        let expected = if self.ctrl {
+            KeyModifiers::CONTROL
+        } else {
+            KeyModifiers::NONE
+        };
+        self.code == event.code && modifiers == expected
+    }
+
+    /// How the key is shown in hints, e.g. `'u'`, `F1` or `Ctrl+x`.
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) if !self.ctrl => return format!("'{}'", c),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            code => format!("{:?}", code),
+        };
+        if self.ctrl {
+            format!("Ctrl+{}", key)
+        } else {
+            key
+        }
+    }
+}
+
+/// One key or several for an action in the config file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Binding {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The keys bound to each action.
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: Action::ALL
+                .iter()
+                .map(|action| {
+                    let keys = action
+                        .default_keys()
+                        .iter()
+                        .map(|k| Key::parse(k).unwrap())
+                        .collect();
+                    (*action, keys)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// `DANGI_DONGI_KEYS`, or `dangi-dongi/keys.json` in the config
+    /// directory.
+    pub fn path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("DANGI_DONGI_KEYS") {
+            return Some(PathBuf::from(path));
+        }
+        Some(crate::config_dir()?.join("keys.json"))
+    }
+
+    /// The keymap from the config file, the defaults without one.
+    pub fn load() -> Result<Keymap, String> {
+        match Keymap::path() {
+            Some(path) if path.exists() => {
+                let data = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                Keymap::from_json(&data).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            _ => Ok(Keymap::default()),
+        }
+    }
+
+    /// Reads `{"quit": "q", "help": ["?", "f1"]}`, actions left out keep
+    /// their default keys.
+    pub fn from_json(data: &str) -> Result<Keymap, String> {
+        let config: BTreeMap<Action, Binding> =
+            serde_json::from_str(data).map_err(|e| format!("invalid key bindings: {}", e))?;
+        let mut keymap = Keymap::default();
+        for (action, binding) in config {
+            let names = match binding {
+                Binding::One(name) => vec![name],
+                Binding::Many(names) => names,
+            };
+            let keys = names
+                .iter()
+                .map(|name| Key::parse(name))
+                .collect::<Result<Vec<Key>, String>>()?;
+            if let Some(entry) = keymap.bindings.iter_mut().find(|(a, _)| *a == action) {
+                entry.1 = keys;
+            }
+        }
+
+        for (i, (action, keys)) in keymap.bindings.iter().enumerate() {
+            for key in keys {
+                if let Some((other, _)) = keymap.bindings[i + 1..]
+                    .iter()
+                    .find(|(_, other_keys)| other_keys.contains(key))
+                {
+                    return Err(format!(
+                        "{} is bound to both {:?} and {:?}",
+                        key.label(),
+                        action,
+                        other
+                    ));
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|k| k.matches(event)))
+            .map(|(action, _)| *action)
+    }
+
+    /// The first key of an action for hints, `-` if it has none.
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, keys)| keys.first())
+            .map_or("-".to_string(), Key::label)
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, keys)| keys)
+    }
+}
@@ -1,41 +1,246 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::fs;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct User {
+/// A member of the group with their running totals.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct User {
     name: String,
     amount_paid: f64,
     net_balance: f64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 pub struct Users {
     users: Vec<User>,
     transactions: Vec<Transaction>,
+    /// Everything that happened to the group, oldest first. `users` and
+    /// `transactions` are what replaying it gives.
+    #[serde(default)]
+    events: Vec<events::Event>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snapshot: Option<events::Snapshot>,
+    /// Transactions changed differently by both sides of a merge, waiting
+    /// to be resolved.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conflicts: Vec<merge::Conflict>,
+    #[serde(default)]
+    presets: Vec<Preset>,
+    #[serde(default)]
+    import_profiles: Vec<import::ImportProfile>,
+    #[serde(default)]
+    accounting: export::AccountingConfig,
+    #[serde(default)]
+    budgets: budget::BudgetConfig,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 pub struct Participant {
     name: String,
     weight: u8,
     fair_share: Option<f64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 pub struct Transaction {
+    /// Stable identity of the transaction, kept across edits and used to
+    /// match it up when merging copies of a group.
+    #[serde(default)]
+    id: String,
     amount: f64,
     payer: String,
     participants: Vec<Participant>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    category: Option<String>,
+    /// Day of the expense as `YYYY-MM-DD`, if known.
+    #[serde(default)]
+    date: Option<String>,
+    /// Money paid back to someone rather than spent, the receiver is the
+    /// only participant with a share.
+    #[serde(default)]
+    repayment: bool,
+}
+
+/// A payment from `from` to `to` that settles (part of) their balances.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+pub enum SplitType {
+    Equal,
+    UnequalByShare,
+    UnequalByAmount,
+}
+
+/// A named template for a recurring expense, e.g. the daily lunch.
+/// Anything left as `None` has to be supplied when the preset is used.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Preset {
+    name: String,
+    payer: Option<String>,
+    /// Participants with their weights. Empty means everyone, equally.
+    participants: Vec<Participant>,
+    split: SplitType,
+    category: Option<String>,
+    amount: Option<f64>,
 }
 
 impl Transaction {
     pub fn new(amount: f64, payer: String, participants: Vec<Participant>) -> Self {
         Transaction {
+            id: String::new(),
             amount,
             payer,
             participants,
+            description: String::new(),
+            category: None,
+            date: None,
+            repayment: false,
         }
     }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_category(mut self, category: Option<String>) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_date(mut self, date: Option<String>) -> Self {
+        self.date = date;
+        self
+    }
+
+    /// Empty until the transaction is recorded.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn payer(&self) -> &str {
+        &self.payer
+    }
+
+    pub fn participants(&self) -> impl Iterator<Item = &Participant> {
+        self.participants.iter()
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+
+    pub fn is_repayment(&self) -> bool {
+        self.repayment
+    }
+
+    /// One line description, as shown in the transactions panel.
+    pub fn summary(&self) -> String {
+        if self.repayment {
+            let receiver = self
+                .participants
+                .iter()
+                .find(|p| p.fair_share.unwrap_or(0.0) > 0.0)
+                .map_or("", |p| p.name.as_str());
+            let mut summary = format!("{} paid {} back {}", self.payer, receiver, self.amount);
+            if let Some(date) = &self.date {
+                summary = format!("{}: {}", date, summary);
+            }
+            return summary;
+        }
+
+        let first_weight = self.participants.first().map(|p| p.weight);
+        let split = if self.participants.iter().all(|p| p.weight == 0) {
+            "split by amount"
+        } else if self
+            .participants
+            .iter()
+            .all(|p| Some(p.weight) == first_weight)
+        {
+            "equally split"
+        } else {
+            "split by share"
+        };
+        let mut summary = format!(
+            "{} paid {} for {}",
+            self.payer, self.amount, self.description
+        );
+        if let Some(date) = &self.date {
+            summary = format!("{}: {}", date, summary);
+        }
+        if let Some(category) = &self.category {
+            summary.push_str(&format!(" [{}]", category));
+        }
+        summary.push_str(&format!(" ({})", split));
+        summary
+    }
+}
+
+impl Preset {
+    pub fn new(name: &str, split: SplitType) -> Self {
+        Preset {
+            name: name.to_string(),
+            payer: None,
+            participants: Vec::new(),
+            split,
+            category: None,
+            amount: None,
+        }
+    }
+
+    pub fn with_payer(mut self, payer: Option<String>) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    pub fn with_participants(mut self, participants: Vec<Participant>) -> Self {
+        self.participants = participants;
+        self
+    }
+
+    pub fn with_category(mut self, category: Option<String>) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_amount(mut self, amount: Option<f64>) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn payer(&self) -> Option<&str> {
+        self.payer.as_deref()
+    }
+
+    pub fn amount(&self) -> Option<f64> {
+        self.amount
+    }
+
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
 }
 
 impl Participant {
@@ -46,6 +251,63 @@ impl Participant {
             fair_share: None,
         }
     }
+
+    /// A participant owing a fixed amount, for splits by amount.
+    pub fn with_share(name: &str, share: f64) -> Self {
+        Participant {
+            name: name.to_string(),
+            weight: 0,
+            fair_share: Some(share),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 0 when the transaction is split by amount.
+    pub fn weight(&self) -> u8 {
+        self.weight
+    }
+
+    /// What the participant owes of the transaction, known once it is
+    /// recorded or split by amount.
+    pub fn share(&self) -> Option<f64> {
+        self.fair_share
+    }
+}
+
+impl User {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Everything the member paid, repayments included.
+    pub fn paid(&self) -> f64 {
+        self.amount_paid
+    }
+
+    /// The member's shares, repayments received included: `paid()` minus
+    /// `balance()`.
+    pub fn consumed(&self) -> f64 {
+        self.amount_paid - self.balance()
+    }
+
+    /// What the member paid minus their shares. Positive means the group
+    /// owes them money.
+    pub fn balance(&self) -> f64 {
+        if self.net_balance.abs() <= 1e-6 {
+            0.0
+        } else {
+            self.net_balance
+        }
+    }
+}
+
+impl Default for Users {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Users {
@@ -53,59 +315,106 @@ impl Users {
         Users {
             users: Vec::new(),
             transactions: Vec::new(),
+            events: Vec::new(),
+            snapshot: None,
+            conflicts: Vec::new(),
+            presets: Vec::new(),
+            import_profiles: Vec::new(),
+            accounting: export::AccountingConfig::default(),
+            budgets: budget::BudgetConfig::default(),
         }
     }
 
     pub fn add_user(&mut self, name: String) {
+        if let Err(e) = self.add_member(&name) {
+            println!("{}.", e);
+        }
+    }
+
+    /// Like `add_user`, but returns what went wrong instead of printing it.
+    pub fn add_member(&mut self, name: &str) -> Result<(), String> {
         if self.users.iter().any(|u| u.name == name) {
-            println!("User {} already exists.", name);
-            return;
+            return Err(format!("User {} already exists", name));
         }
-        self.users.push(User {
-            name,
-            amount_paid: 0.0,
-            net_balance: 0.0,
-        });
+        self.record(events::Event::MemberAdded {
+            name: name.to_string(),
+        })
     }
 
     pub fn list_users(&self) -> Vec<String> {
         self.users.iter().map(|u| u.name.clone()).collect()
     }
 
+    pub fn members(&self) -> impl Iterator<Item = &User> {
+        self.users.iter()
+    }
+
+    pub fn member(&self, name: &str) -> Option<&User> {
+        self.users.iter().find(|u| u.name == name)
+    }
+
+    /// The transactions since the group last settled up, oldest first.
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.transactions.iter()
+    }
+
     pub fn remove_user(&mut self, name: String) {
+        if let Err(e) = self.remove_member(&name) {
+            println!("{}.", e);
+        }
+    }
+
+    /// Like `remove_user`, but returns what went wrong instead of printing it.
+    pub fn remove_member(&mut self, name: &str) -> Result<(), String> {
         if !self.users.iter().any(|u| u.name == name) {
-            println!("User {} does not exist.", name);
-            return;
+            return Err(format!("User {} does not exist", name));
+        }
+        if self
+            .transactions
+            .iter()
+            .any(|t| t.payer == name || t.participants.iter().any(|p| p.name == name))
+        {
+            return Err(format!(
+                "User {} has transactions and cannot be removed",
+                name
+            ));
         }
-        self.users.retain(|u| u.name != name);
+        self.record(events::Event::MemberRemoved {
+            name: name.to_string(),
+        })
     }
 
     pub fn record_payment(&mut self, user: &str, amount: f64) {
-        match self.users.iter_mut().find(|u| u.name == user) {
-            Some(u) => {
-                u.amount_paid += amount;
-                self.transactions.push(Transaction {
-                    amount,
-                    payer: user.to_string(),
-                    participants: self
-                        .users
-                        .iter()
-                        .map(|u| Participant {
-                            name: u.name.clone(),
-                            weight: 1,
-                            fair_share: Some(amount / self.users.len() as f64),
-                        })
-                        .collect(),
-                });
-            }
-            None => {
-                println!("User {} not found.", user);
-            }
+        if !self.users.iter().any(|u| u.name == user) {
+            println!("User {} not found.", user);
+            return;
         }
+        let participants = self
+            .users
+            .iter()
+            .map(|u| Participant {
+                name: u.name.clone(),
+                weight: 1,
+                fair_share: Some(amount / self.users.len() as f64),
+            })
+            .collect();
+        let transaction = Transaction::new(amount, user.to_string(), participants);
+        let _ = self.record(events::Event::ExpenseRecorded { transaction });
     }
 
     pub fn record_weighted_payment(&mut self, user: &str, mut transaction: Transaction) {
         let amount = transaction.amount;
+        transaction.payer = user.to_string();
+        match self.add_transaction(transaction) {
+            Ok(()) => println!("{} for user {} added.", amount, user),
+            Err(e) => println!("{}.", e),
+        }
+    }
+
+    /// Records a transaction paid by its payer, computing the shares from
+    /// the weights. Every user has to be a participant.
+    pub fn add_transaction(&mut self, mut transaction: Transaction) -> Result<(), String> {
+        check_amount(transaction.amount)?;
         let participants: Vec<String> = transaction
             .participants
             .iter()
@@ -117,192 +426,386 @@ impl Users {
         if !(participants.len() == all_users.len()
             && participants.iter().all(|p| all_users.contains(p)))
         {
-            println!("Participants are invalid.");
-            return;
+            return Err("Participants are invalid".to_string());
         }
 
-        calculate_fair_shares(&mut transaction);
+        calculate_fair_shares(&mut transaction)?;
 
-        match self.users.iter_mut().find(|u| u.name == user) {
-            Some(u) => {
-                u.amount_paid += amount;
-                println!("{} for user {} added.", amount, user);
-                self.transactions.push(transaction);
-            }
-            None => {
-                println!("User {} not found.", user);
+        if !self.users.iter().any(|u| u.name == transaction.payer) {
+            return Err(format!("User {} not found", transaction.payer));
+        }
+        let event = if transaction.repayment {
+            events::Event::RepaymentRecorded { transaction }
+        } else {
+            events::Event::ExpenseRecorded { transaction }
+        };
+        self.record(event)
+    }
+
+    /// Replaces the transaction at `index`, recomputing its shares.
+    pub fn edit_transaction(
+        &mut self,
+        index: usize,
+        mut transaction: Transaction,
+    ) -> Result<(), String> {
+        if index >= self.transactions.len() {
+            return Err(format!("Transaction index {} is out of bounds", index));
+        }
+        check_amount(transaction.amount)?;
+        calculate_fair_shares(&mut transaction)?;
+        self.record(events::Event::ExpenseEdited { index, transaction })
+    }
+
+    /// Records `from` paying `to` back, outside of any expense.
+    pub fn record_repayment(
+        &mut self,
+        from: &str,
+        to: &str,
+        amount: f64,
+        date: Option<String>,
+    ) -> Result<(), String> {
+        for name in [from, to] {
+            if !self.users.iter().any(|u| u.name == name) {
+                return Err(format!("User {} not found", name));
             }
         }
+        if from == to || amount <= 0.0 {
+            return Err("Repayment is invalid".to_string());
+        }
+        let participants = self
+            .users
+            .iter()
+            .map(|u| Participant::with_share(&u.name, if u.name == to { amount } else { 0.0 }))
+            .collect();
+        let mut transaction =
+            Transaction::new(amount, from.to_string(), participants).with_date(date);
+        transaction.repayment = true;
+        self.add_transaction(transaction)
+    }
+
+    /// Net balance of every user: what they paid minus their fair shares.
+    /// Positive means the group owes them money.
+    pub fn balances(&self) -> Vec<(String, f64)> {
+        self.users
+            .iter()
+            .map(|u| (u.name.clone(), u.balance()))
+            .collect()
     }
 
+    /// Removes the latest transaction `user` paid `amount` for.
     pub fn remove_payment(&mut self, user: &str, amount: f64) {
-        match self.users.iter_mut().find(|u| u.name == user) {
-            Some(u) => {
-                u.amount_paid -= amount;
-                if u.amount_paid < 0.0 {
-                    println!("Amount paid for user {} is negative. Setting to 0.", user);
-                    u.amount_paid = 0.0;
-                }
+        match self
+            .transactions
+            .iter()
+            .rposition(|t| t.payer == user && (t.amount - amount).abs() < 0.005)
+        {
+            Some(index) => {
+                self.remove_payment_by_index(index);
                 println!("{} for user {} removed.", amount, user);
             }
             None => {
-                println!("User {} not found.", user);
+                println!("No payment of {} by user {} found.", amount, user);
             }
         }
     }
 
     pub fn remove_payment_by_index(&mut self, index: usize) {
-        if index >= self.transactions.len() {
-            println!("Transaction index {} is out of bounds.", index);
-            return;
+        if let Err(e) = self.remove_transaction(index) {
+            println!("{}.", e);
         }
-        for user in &mut self.users {
-            if user.name == self.transactions[index].payer {
-                user.amount_paid -= self.transactions[index].amount;
-                if user.amount_paid < 0.0 {
-                    panic!(
-                        "Amount paid for user {} is negative after removing transaction. This should not happen.",
-                        self.transactions[index].payer
-                    );
-                }
-            }
+    }
+
+    /// Like `remove_payment_by_index`, but returns what went wrong instead
+    /// of printing it.
+    pub fn remove_transaction(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.transactions.len() {
+            return Err(format!("Transaction index {} is out of bounds", index));
         }
-        self.transactions.remove(index);
+        self.record(events::Event::ExpenseRemoved { index })
     }
 
-    pub fn calculate_total_payments(&mut self) -> Result<Vec<String>, String> {
+    pub fn calculate_total_payments(&self) -> Result<Vec<String>, String> {
         if self.users.len() < 2 {
             println!("Not enough users to calculate payments.");
             return Err("Not enough users".to_string());
         }
 
-        let mut results = Vec::new();
+        Ok(self
+            .settlement()
+            .iter()
+            .map(|t| format!("{} should pay {} {:.2}", t.from, t.to, t.amount))
+            .collect())
+    }
+
+    /// The transfers that settle all balances, largest debts first.
+    pub fn settlement(&self) -> Vec<Transfer> {
+        let eps = 1e-6;
+        let balances = self.balances();
+        let mut creditors: Vec<(String, f64)> = balances
+            .iter()
+            .filter(|(_, balance)| *balance > eps)
+            .cloned()
+            .collect();
+        let mut debtors: Vec<(String, f64)> = balances
+            .iter()
+            .filter(|(_, balance)| *balance < -eps)
+            .cloned()
+            .collect();
 
-        for user in &mut self.users {
-            let mut total_fair_share = 0.0;
-            for transaction in &self.transactions {
-                total_fair_share += transaction
-                    .participants
-                    .iter()
-                    .find(|p| p.name == user.name)
-                    .unwrap()
-                    .fair_share
-                    .unwrap_or(0.0);
+        creditors.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        debtors.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let mut results = Vec::new();
+        let mut i = 0usize;
+        let mut j = 0usize;
+        while i < creditors.len() && j < debtors.len() {
+            let available = creditors[i].1;
+            let owed = -debtors[j].1;
+            let transfer = available.min(owed);
+
+            results.push(Transfer {
+                from: debtors[j].0.clone(),
+                to: creditors[i].0.clone(),
+                amount: transfer,
+            });
+
+            creditors[i].1 -= transfer;
+            debtors[j].1 += transfer; // debtors store negative values
+
+            if creditors[i].1 <= eps {
+                i += 1;
+            }
+            if debtors[j].1 >= -eps {
+                j += 1;
             }
-            user.net_balance = user.amount_paid - total_fair_share;
         }
+        results
+    }
 
-        // for transaction in &self.transactions {
-        //     for participant in &transaction.participants {
-        //         let participant_name = &participant.name;
-        //         let fair_share = &participant.fair_share.unwrap_or(0.0);
-        //         for (name, user) in &mut self.users {
-        //             if name == participant_name {
-        //                 user.net_balance += user.amount_paid - fair_share;
-        //             }
-        //         }
-        //     }
-        // }
+    pub fn transaction_summaries(&self) -> Vec<String> {
+        self.transactions.iter().map(|t| t.summary()).collect()
+    }
 
+    pub fn add_preset(&mut self, preset: Preset) -> Result<(), String> {
+        if preset.split == SplitType::UnequalByAmount {
+            return Err("Presets cannot split by amount".to_string());
+        }
+        if let Some(amount) = preset.amount {
+            check_amount(amount)?;
+        }
+        if let Some(payer) = &preset.payer
+            && !self.users.iter().any(|u| &u.name == payer)
         {
-            let eps = 1e-6;
-            let mut creditors: Vec<(String, f64)> = self
-                .users
-                .iter()
-                .filter_map(|user| {
-                    if user.net_balance > eps {
-                        Some((user.name.clone(), user.net_balance))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+            return Err(format!("User {} not found", payer));
+        }
+        if let Some(p) = preset
+            .participants
+            .iter()
+            .find(|p| !self.users.iter().any(|u| u.name == p.name))
+        {
+            return Err(format!("User {} not found", p.name));
+        }
+        self.presets.retain(|p| p.name != preset.name);
+        self.presets.push(preset);
+        Ok(())
+    }
 
-            let mut debtors: Vec<(String, f64)> = self
-                .users
-                .iter()
-                .filter_map(|user| {
-                    if user.net_balance < -eps {
-                        Some((user.name.clone(), user.net_balance))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-
-            creditors.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-            debtors.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-
-            let mut i = 0usize;
-            let mut j = 0usize;
-            while i < creditors.len() && j < debtors.len() {
-                let available = creditors[i].1;
-                let owed = -debtors[j].1;
-                let transfer = available.min(owed);
-
-                results.push(format!(
-                    "{} should pay {} {:.2}",
-                    debtors[j].0, creditors[i].0, transfer
-                ));
-
-                creditors[i].1 -= transfer;
-                debtors[j].1 += transfer; // debtors store negative values
-
-                if creditors[i].1 <= eps {
-                    i += 1;
-                }
-                if debtors[j].1 >= -eps {
-                    j += 1;
-                }
-            }
+    pub fn remove_preset(&mut self, name: &str) -> Result<(), String> {
+        if !self.presets.iter().any(|p| p.name == name) {
+            return Err(format!("Preset {} does not exist", name));
+        }
+        self.presets.retain(|p| p.name != name);
+        Ok(())
+    }
 
-            for user in &mut self.users {
-                if user.net_balance.abs() <= eps {
-                    user.net_balance = 0.0;
-                }
-            }
-            Ok(results)
+    pub fn presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    pub fn find_preset(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|p| p.name == name)
+    }
+
+    /// Records an expense from a preset. `amount` and `payer` override the
+    /// preset's defaults and are required when the preset has none.
+    pub fn record_preset(
+        &mut self,
+        name: &str,
+        amount: Option<f64>,
+        payer: Option<&str>,
+    ) -> Result<(), String> {
+        let transaction = self
+            .preset_transaction(name, amount, payer)?
+            .with_date(Some(today()));
+        self.add_transaction(transaction)
+    }
+
+    /// Builds the transaction a preset stands for, without recording it.
+    pub fn preset_transaction(
+        &self,
+        name: &str,
+        amount: Option<f64>,
+        payer: Option<&str>,
+    ) -> Result<Transaction, String> {
+        let preset = self
+            .find_preset(name)
+            .ok_or(format!("Preset {} does not exist", name))?;
+        let amount = amount
+            .or(preset.amount)
+            .ok_or(format!("Preset {} has no default amount", name))?;
+        let payer = payer
+            .map(|p| p.to_string())
+            .or(preset.payer.clone())
+            .ok_or(format!("Preset {} has no default payer", name))?;
+        if !self.users.iter().any(|u| u.name == payer) {
+            return Err(format!("User {} not found", payer));
+        }
+
+        // Every user takes part in a transaction, the ones left out of the
+        // preset just carry no weight.
+        let participants: Vec<Participant> = self
+            .users
+            .iter()
+            .map(|u| {
+                let weight = if preset.participants.is_empty() {
+                    1
+                } else {
+                    match preset.participants.iter().find(|p| p.name == u.name) {
+                        Some(p) if preset.split == SplitType::UnequalByShare => p.weight,
+                        Some(_) => 1,
+                        None => 0,
+                    }
+                };
+                Participant::new(&u.name, weight)
+            })
+            .collect();
+        if participants.iter().all(|p| p.weight == 0) {
+            return Err(format!("Preset {} has no participants left", name));
         }
+
+        Ok(Transaction::new(amount, payer, participants)
+            .with_description(&preset.name)
+            .with_category(preset.category.clone()))
     }
 
     pub fn settle_up(&mut self) {
-        for user in &mut self.users {
-            user.net_balance = 0.0;
-            user.amount_paid = 0.0;
-        }
-        self.transactions.clear();
+        let _ = self.record(events::Event::SettledUp);
     }
 
     pub fn save_to_file(&self, file_path: &str) {
-        match serde_json::to_writer_pretty(std::fs::File::create(file_path).unwrap(), &self) {
-            Ok(_) => {}
-            Err(e) => println!("Error saving users: {}", e),
+        if let Err(e) = storage::save(self, file_path) {
+            println!("Error saving users: {}", e);
         }
     }
 }
 
 pub fn load_from_file(file_path: &str) -> Option<Users> {
-    let data = fs::read_to_string(file_path).expect("Unable to read file");
-    let users_str: Users = match serde_json::from_str(&data) {
-        Ok(v) => v,
+    match format::load(file_path) {
+        Ok(users) => Some(users),
         Err(e) => {
-            println!("Error parsing JSON: {}", e);
-            return None;
+            println!("{}", e);
+            None
         }
-    };
-    Some(users_str)
+    }
+}
+
+/// The rule every amount entered or loaded follows: any finite amount but
+/// zero, a negative one being a refund.
+pub fn check_amount(amount: f64) -> Result<(), String> {
+    if amount.is_finite() && amount != 0.0 {
+        Ok(())
+    } else {
+        Err(format!("Invalid amount: {}", amount))
+    }
 }
 
-fn calculate_fair_shares(transaction: &mut Transaction) {
+fn calculate_fair_shares(transaction: &mut Transaction) -> Result<(), String> {
     let amount = transaction.amount;
     let total_weight: u32 = transaction
         .participants
         .iter()
-        .map(|p| p.weight)
-        .sum::<u8>() as u32;
+        .map(|p| p.weight as u32)
+        .sum();
+
+    // Split by amount: the shares are given and must add up.
+    if total_weight == 0 {
+        let total: Option<f64> = transaction.participants.iter().map(|p| p.fair_share).sum();
+        return match total {
+            Some(total) if (total - amount).abs() < 0.005 => Ok(()),
+            _ => Err("Shares do not add up to the amount".to_string()),
+        };
+    }
+
     for p in &mut transaction.participants {
         p.fair_share = Some(amount * (p.weight as f64) / (total_weight as f64));
     }
+    Ok(())
+}
+
+/// `$XDG_CONFIG_HOME/dangi-dongi`, or `~/.config/dangi-dongi`.
+pub(crate) fn config_dir() -> Option<std::path::PathBuf> {
+    let config = std::env::var("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|_| {
+            std::env::var("HOME").map(|home| std::path::PathBuf::from(home).join(".config"))
+        })
+        .ok()?;
+    Some(config.join("dangi-dongi"))
+}
+
+/// Today's date as `YYYY-MM-DD`, in UTC.
+pub(crate) fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    date_from_days((secs / 86_400) as i64)
+}
+
+/// Turns a `YYYY-MM-DD` date into days since 1970-01-01.
+pub(crate) fn days_from_date(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    // Howard Hinnant's days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+/// Turns days since 1970-01-01 into a `YYYY-MM-DD` date.
+pub(crate) fn date_from_days(days: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
 }
 
+pub use builder::TransactionBuilder;
+
+pub mod budget;
+pub mod builder;
+pub mod cli;
+pub mod crypto;
+pub mod events;
+pub mod export;
+pub mod filter;
+pub mod format;
+pub mod import;
+pub mod input;
+pub mod keymap;
+pub mod merge;
+pub mod reports;
+pub mod server;
+pub mod storage;
+pub mod sync;
+pub mod theme;
 pub mod tui;
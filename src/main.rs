@@ -1,6 +1,9 @@
 use std::io;
 
 fn main() -> io::Result<()> {
-    dangi_dongi::tui::start_tui().unwrap();
+    if let Err(e) = dangi_dongi::cli::run(std::env::args().skip(1).collect()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
     Ok(())
 }
@@ -0,0 +1,217 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::events::Event;
+use crate::{Transaction, Users};
+
+/// A transaction both sides of a merge changed differently since their
+/// common ancestor. Until it is resolved, the group keeps our version.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Conflict {
+    id: String,
+    /// Our version, `None` if we removed the transaction.
+    ours: Option<Transaction>,
+    /// Their version, `None` if they removed the transaction.
+    theirs: Option<Transaction>,
+}
+
+impl Conflict {
+    /// One line description of our version.
+    pub fn ours(&self) -> String {
+        describe(&self.ours)
+    }
+
+    /// One line description of their version.
+    pub fn theirs(&self) -> String {
+        describe(&self.theirs)
+    }
+}
+
+fn describe(version: &Option<Transaction>) -> String {
+    version
+        .as_ref()
+        .map_or("removed".to_string(), |t| t.summary())
+}
+
+/// What a merge took over from their side.
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    pub members_added: usize,
+    pub members_removed: usize,
+    pub added: usize,
+    pub edited: usize,
+    pub removed: usize,
+    pub conflicts: usize,
+}
+
+/// Three-way merge of two copies of a group that were edited separately
+/// since `base`.
+///
+/// The result is `ours` with their changes recorded on top, so our log
+/// stays as it was and theirs is appended as new events. Members are
+/// matched by name and transactions by id. A transaction changed on one
+/// side only takes that change, one changed differently on both sides is
+/// kept as ours and added to the conflicts.
+pub fn merge(base: &Users, ours: &Users, theirs: &Users) -> Result<(Users, MergeReport), String> {
+    let mut merged = ours.clone();
+    let mut report = MergeReport::default();
+    let in_base = |name: &str| base.users.iter().any(|u| u.name == name);
+    let used_by_theirs = |name: &str| {
+        theirs
+            .transactions
+            .iter()
+            .any(|t| t.payer == name || t.participants.iter().any(|p| p.name == name))
+    };
+
+    // Their new members, and ours they still need for their transactions.
+    for user in &theirs.users {
+        if !merged.is_member(&user.name) && (!in_base(&user.name) || used_by_theirs(&user.name)) {
+            merged.record(Event::MemberAdded {
+                name: user.name.clone(),
+            })?;
+            report.members_added += 1;
+        }
+    }
+
+    for t in &theirs.transactions {
+        let base_version = find(base, &t.id);
+        let our_version = find(&merged, &t.id).cloned();
+        match (base_version, our_version) {
+            (None, None) => {
+                merged.record(recorded(t))?;
+                report.added += 1;
+            }
+            (Some(b), None) if b != t => merged.conflict(&t.id, None, Some(t.clone())),
+            (Some(_), None) => {}
+            (Some(b), Some(o)) if o == *b && t != b => {
+                let Some(index) = merged.position(&t.id) else {
+                    return Err(format!("Transaction {} not found", t.id));
+                };
+                merged.record(Event::ExpenseEdited {
+                    index,
+                    transaction: t.clone(),
+                })?;
+                report.edited += 1;
+            }
+            (b, Some(o)) if o != *t && b != Some(t) => {
+                merged.conflict(&t.id, Some(o), Some(t.clone()))
+            }
+            _ => {}
+        }
+    }
+
+    for b in &base.transactions {
+        if find(theirs, &b.id).is_some() {
+            continue;
+        }
+        match find(&merged, &b.id).cloned() {
+            Some(o) if o == *b => {
+                let Some(index) = merged.position(&b.id) else {
+                    return Err(format!("Transaction {} not found", b.id));
+                };
+                merged.record(Event::ExpenseRemoved { index })?;
+                report.removed += 1;
+            }
+            Some(o) => merged.conflict(&b.id, Some(o), None),
+            None => {}
+        }
+    }
+
+    // Members they removed, unless we still need them.
+    for user in &base.users {
+        if !theirs.is_member(&user.name)
+            && merged.is_member(&user.name)
+            && !merged
+                .transactions
+                .iter()
+                .any(|t| t.payer == user.name || t.participants.iter().any(|p| p.name == user.name))
+        {
+            merged.record(Event::MemberRemoved {
+                name: user.name.clone(),
+            })?;
+            report.members_removed += 1;
+        }
+    }
+
+    // Settings are not in the log, ours win and theirs fill in the gaps.
+    for preset in &theirs.presets {
+        if merged.find_preset(&preset.name).is_none() {
+            merged.presets.push(preset.clone());
+        }
+    }
+    for profile in &theirs.import_profiles {
+        if !merged
+            .import_profiles
+            .iter()
+            .any(|p| p.name() == profile.name())
+        {
+            merged.import_profiles.push(profile.clone());
+        }
+    }
+    merged.budgets.fill_in(&theirs.budgets);
+    for conflict in &theirs.conflicts {
+        if !merged.conflicts.iter().any(|c| c.id == conflict.id) {
+            merged.conflicts.push(conflict.clone());
+        }
+    }
+
+    report.conflicts = merged.conflicts.len();
+    Ok((merged, report))
+}
+
+impl Users {
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Settles a merge conflict by keeping our version or taking theirs.
+    pub fn resolve_conflict(&mut self, index: usize, take_theirs: bool) -> Result<(), String> {
+        let conflict = self
+            .conflicts
+            .get(index)
+            .ok_or(format!("There is no conflict {}", index + 1))?
+            .clone();
+        if take_theirs {
+            let current = self.transactions.iter().position(|t| t.id == conflict.id);
+            match (current, conflict.theirs) {
+                (Some(index), Some(transaction)) => {
+                    self.record(Event::ExpenseEdited { index, transaction })?
+                }
+                (Some(index), None) => self.record(Event::ExpenseRemoved { index })?,
+                (None, Some(transaction)) => self.record(recorded(&transaction))?,
+                (None, None) => {}
+            }
+        }
+        self.conflicts.remove(index);
+        Ok(())
+    }
+
+    fn conflict(&mut self, id: &str, ours: Option<Transaction>, theirs: Option<Transaction>) {
+        self.conflicts.retain(|c| c.id != id);
+        self.conflicts.push(Conflict {
+            id: id.to_string(),
+            ours,
+            theirs,
+        });
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.transactions.iter().position(|t| t.id == id)
+    }
+}
+
+fn find<'a>(users: &'a Users, id: &str) -> Option<&'a Transaction> {
+    users.transactions.iter().find(|t| t.id == id)
+}
+
+fn recorded(transaction: &Transaction) -> Event {
+    if transaction.repayment {
+        Event::RepaymentRecorded {
+            transaction: transaction.clone(),
+        }
+    } else {
+        Event::ExpenseRecorded {
+            transaction: transaction.clone(),
+        }
+    }
+}
@@ -0,0 +1,463 @@
+use serde::Serialize;
+
+use crate::events::Event;
+use crate::{Transaction, Users};
+
+/// What a member spent and used up over a set of expenses.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MemberStats {
+    pub name: String,
+    /// Expenses the member paid for.
+    pub paid: f64,
+    /// The member's shares of all expenses.
+    pub consumed: f64,
+    /// `paid` minus `consumed`.
+    pub net: f64,
+}
+
+/// Spending of one category or month.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Breakdown {
+    pub name: String,
+    pub spent: f64,
+    pub expenses: usize,
+}
+
+/// Spending statistics of a group. Repayments move money around without
+/// anything being spent, so they are left out.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Report {
+    pub total: f64,
+    pub members: Vec<MemberStats>,
+    /// Largest first, expenses without a category under "uncategorized".
+    pub categories: Vec<Breakdown>,
+    /// `YYYY-MM`, oldest first, expenses without a date under "undated".
+    pub months: Vec<Breakdown>,
+    /// `YYYY-MM-DD`, oldest first, only the expenses with a date.
+    pub days: Vec<Breakdown>,
+}
+
+/// Every member's balance after each change to the group, for plotting.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct BalanceHistory {
+    /// Whether x is a day, counted from 1970-01-01. It is only when every
+    /// transaction has a date, otherwise x counts the changes.
+    pub dated: bool,
+    /// Member names with their `(x, balance)` points.
+    pub members: Vec<(String, Vec<(f64, f64)>)>,
+}
+
+/// One transaction as it touches a member.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MemberEntry {
+    pub date: Option<String>,
+    pub description: String,
+    pub payer: String,
+    /// A repayment between two members rather than an expense.
+    pub repayment: bool,
+    /// The amount, if the member paid it.
+    pub paid: f64,
+    /// The member's `fair_share` of it.
+    pub share: f64,
+    /// The member's net balance once it is counted.
+    pub balance: f64,
+}
+
+/// A member's statement since the group last settled up. The balance is
+/// worked out the way the group keeps it, so it ends where the settlement
+/// starts from.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MemberDetail {
+    pub name: String,
+    /// Expenses the member paid for.
+    pub paid: f64,
+    /// The member's shares of the expenses.
+    pub consumed: f64,
+    /// Repayments the member made.
+    pub repaid: f64,
+    /// Repayments the member got.
+    pub received: f64,
+    pub balance: f64,
+    /// Oldest first.
+    pub entries: Vec<MemberEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "text" | "txt" => Some(ReportFormat::Text),
+            "csv" => Some(ReportFormat::Csv),
+            "json" => Some(ReportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl Report {
+    /// Statistics over `transactions`, with a row for each of `members`
+    /// and anyone else who took part.
+    pub fn new(members: &[String], transactions: &[Transaction]) -> Report {
+        let mut report = Report {
+            members: members
+                .iter()
+                .map(|name| MemberStats {
+                    name: name.clone(),
+                    paid: 0.0,
+                    consumed: 0.0,
+                    net: 0.0,
+                })
+                .collect(),
+            ..Report::default()
+        };
+
+        for t in transactions.iter().filter(|t| !t.repayment) {
+            report.total += t.amount;
+            report.member(&t.payer).paid += t.amount;
+            for p in &t.participants {
+                report.member(&p.name).consumed += p.fair_share.unwrap_or(0.0);
+            }
+            let category = t.category.as_deref().unwrap_or("uncategorized");
+            add(&mut report.categories, category, t.amount);
+            let month = t
+                .date
+                .as_ref()
+                .and_then(|d| d.get(..7))
+                .unwrap_or("undated");
+            add(&mut report.months, month, t.amount);
+            if let Some(date) = &t.date {
+                add(&mut report.days, date, t.amount);
+            }
+        }
+
+        for member in &mut report.members {
+            member.net = member.paid - member.consumed;
+        }
+        report
+            .categories
+            .sort_by(|a, b| b.spent.total_cmp(&a.spent).then(a.name.cmp(&b.name)));
+        // "undated" sorts after any year.
+        report.months.sort_by(|a, b| a.name.cmp(&b.name));
+        report.days.sort_by(|a, b| a.name.cmp(&b.name));
+        report
+    }
+
+    fn member(&mut self, name: &str) -> &mut MemberStats {
+        match self.members.iter().position(|m| m.name == name) {
+            Some(i) => &mut self.members[i],
+            None => {
+                self.members.push(MemberStats {
+                    name: name.to_string(),
+                    paid: 0.0,
+                    consumed: 0.0,
+                    net: 0.0,
+                });
+                self.members.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.to_text(),
+            ReportFormat::Csv => self.to_csv(),
+            ReportFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default() + "\n",
+        }
+    }
+
+    /// Aligned tables for the terminal.
+    pub fn to_text(&self) -> String {
+        let width = self
+            .members
+            .iter()
+            .map(|m| m.name.chars().count())
+            .chain(self.categories.iter().map(|c| c.name.chars().count()))
+            .chain(["Category".len()])
+            .max()
+            .unwrap_or(0);
+        let mut out = format!("Total spent: {:.2}\n\n", self.total);
+        out.push_str(&format!(
+            "{:<width$}  {:>10}  {:>10}  {:>10}\n",
+            "Member", "Paid", "Consumed", "Net"
+        ));
+        for m in &self.members {
+            out.push_str(&format!(
+                "{:<width$}  {:>10.2}  {:>10.2}  {:>+10.2}\n",
+                m.name, m.paid, m.consumed, m.net
+            ));
+        }
+        for (title, rows) in [("Category", &self.categories), ("Month", &self.months)] {
+            out.push_str(&format!(
+                "\n{:<width$}  {:>10}  {:>10}\n",
+                title, "Spent", "Expenses"
+            ));
+            for row in rows {
+                out.push_str(&format!(
+                    "{:<width$}  {:>10.2}  {:>10}\n",
+                    row.name, row.spent, row.expenses
+                ));
+            }
+        }
+        out
+    }
+
+    /// One row per member, category and month, told apart by the first
+    /// column.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("breakdown,name,paid,consumed,net,spent,expenses\n");
+        for m in &self.members {
+            out.push_str(&format!(
+                "member,{},{:.2},{:.2},{:.2},,\n",
+                crate::export::csv_field(&m.name),
+                m.paid,
+                m.consumed,
+                m.net
+            ));
+        }
+        let breakdowns = [
+            ("category", &self.categories),
+            ("month", &self.months),
+            ("day", &self.days),
+        ];
+        for (kind, rows) in breakdowns {
+            for row in rows {
+                out.push_str(&format!(
+                    "{},{},,,,{:.2},{}\n",
+                    kind,
+                    crate::export::csv_field(&row.name),
+                    row.spent,
+                    row.expenses
+                ));
+            }
+        }
+        out
+    }
+}
+
+impl MemberEntry {
+    /// The description, repayments marked as such.
+    pub fn label(&self) -> String {
+        match (self.repayment, self.description.is_empty()) {
+            (true, true) => "repayment".to_string(),
+            (true, false) => format!("{} (repayment)", self.description),
+            (false, _) => self.description.clone(),
+        }
+    }
+}
+
+impl MemberDetail {
+    /// The totals and an aligned line per transaction.
+    pub fn to_text(&self) -> String {
+        let width = self
+            .entries
+            .iter()
+            .map(|e| e.label().chars().count())
+            .chain(["Description".len()])
+            .max()
+            .unwrap_or(0);
+        let mut out = format!(
+            "{}: paid {:.2}, consumed {:.2}, repaid {:.2}, received {:.2}, balance {:+.2}\n\n",
+            self.name, self.paid, self.consumed, self.repaid, self.received, self.balance
+        );
+        out.push_str(&format!(
+            "{:<10}  {:<width$}  {:>10}  {:>10}  {:>10}\n",
+            "Date", "Description", "Paid", "Share", "Balance"
+        ));
+        for e in &self.entries {
+            out.push_str(&format!(
+                "{:<10}  {:<width$}  {:>10.2}  {:>10.2}  {:>+10.2}\n",
+                e.date.as_deref().unwrap_or("-"),
+                e.label(),
+                e.paid,
+                e.share,
+                e.balance
+            ));
+        }
+        out
+    }
+}
+
+fn add(breakdowns: &mut Vec<Breakdown>, name: &str, amount: f64) {
+    match breakdowns.iter_mut().find(|b| b.name == name) {
+        Some(b) => {
+            b.spent += amount;
+            b.expenses += 1;
+        }
+        None => breakdowns.push(Breakdown {
+            name: name.to_string(),
+            spent: amount,
+            expenses: 1,
+        }),
+    }
+}
+
+impl Users {
+    /// Statistics over everything the group spent, including what was
+    /// settled up since.
+    pub fn report(&self) -> Report {
+        Report::new(&self.list_users(), &self.all_transactions())
+    }
+
+    /// Statistics over the open expenses only, as `member_detail` counts
+    /// them.
+    pub fn open_report(&self) -> Report {
+        Report::new(&self.list_users(), &self.transactions)
+    }
+
+    /// How the balances went, replayed from the log. Settling up brings
+    /// everyone back to zero.
+    pub fn balance_history(&self) -> BalanceHistory {
+        let recorded = self.events.iter().filter_map(|e| match e {
+            Event::ExpenseRecorded { transaction }
+            | Event::RepaymentRecorded { transaction }
+            | Event::ExpenseEdited { transaction, .. } => Some(transaction),
+            _ => None,
+        });
+        let dated = recorded
+            .clone()
+            .all(|t| t.date.as_deref().and_then(crate::days_from_date).is_some())
+            && recorded.clone().next().is_some();
+
+        let mut history = BalanceHistory {
+            dated,
+            members: Vec::new(),
+        };
+        let mut balances: Vec<(String, f64)> = Vec::new();
+        let mut open: Vec<Transaction> = Vec::new();
+        let mut x = 0.0;
+        for event in &self.events {
+            let mut book = |t: &Transaction, sign: f64| {
+                let mut add =
+                    |name: &str, amount: f64| match balances.iter_mut().find(|b| b.0 == name) {
+                        Some(b) => b.1 += sign * amount,
+                        None => balances.push((name.to_string(), sign * amount)),
+                    };
+                add(&t.payer, t.amount);
+                for p in &t.participants {
+                    add(&p.name, -p.fair_share.unwrap_or(0.0));
+                }
+            };
+            let date = match event {
+                Event::ExpenseRecorded { transaction }
+                | Event::RepaymentRecorded { transaction } => {
+                    book(transaction, 1.0);
+                    open.push(transaction.clone());
+                    transaction.date.clone()
+                }
+                Event::ExpenseEdited { index, transaction } if *index < open.len() => {
+                    book(&open[*index], -1.0);
+                    book(transaction, 1.0);
+                    open[*index] = transaction.clone();
+                    transaction.date.clone()
+                }
+                Event::ExpenseRemoved { index } if *index < open.len() => {
+                    book(&open.remove(*index), -1.0);
+                    None
+                }
+                Event::SettledUp => {
+                    open.clear();
+                    for balance in &mut balances {
+                        balance.1 = 0.0;
+                    }
+                    None
+                }
+                _ => continue,
+            };
+
+            x = match date.as_deref().and_then(crate::days_from_date) {
+                Some(day) if dated => day as f64,
+                _ if dated => x,
+                _ => x + 1.0,
+            };
+            for (name, balance) in &balances {
+                match history.members.iter_mut().find(|m| m.0 == *name) {
+                    Some(member) => member.1.push((x, *balance)),
+                    None => history.members.push((name.clone(), vec![(x, *balance)])),
+                }
+            }
+        }
+        history
+    }
+
+    /// The statement of `name` over the open transactions.
+    pub fn member_detail(&self, name: &str) -> Option<MemberDetail> {
+        let user = self.member(name)?;
+        let mut detail = MemberDetail {
+            name: user.name.clone(),
+            paid: 0.0,
+            consumed: 0.0,
+            repaid: 0.0,
+            received: 0.0,
+            balance: 0.0,
+            entries: Vec::new(),
+        };
+        let mut balance = 0.0;
+        for t in &self.transactions {
+            let paid = if t.payer == name { t.amount } else { 0.0 };
+            let share: f64 = t
+                .participants
+                .iter()
+                .filter(|p| p.name == name)
+                .map(|p| p.fair_share.unwrap_or(0.0))
+                .sum();
+            if t.payer != name && share == 0.0 {
+                continue;
+            }
+            // The same booking as the group's balances.
+            balance += paid;
+            balance -= share;
+            if t.repayment {
+                detail.repaid += paid;
+                detail.received += share;
+            } else {
+                detail.paid += paid;
+                detail.consumed += share;
+            }
+            detail.entries.push(MemberEntry {
+                date: t.date.clone(),
+                description: t.description.clone(),
+                payer: t.payer.clone(),
+                repayment: t.repayment,
+                paid,
+                share,
+                balance,
+            });
+        }
+        detail.balance = user.balance();
+        Some(detail)
+    }
+
+    /// Every transaction the log has in effect, the settled ones first.
+    /// Edits and removals are taken into account.
+    pub fn all_transactions(&self) -> Vec<Transaction> {
+        let mut settled = Vec::new();
+        let mut open: Vec<Transaction> = Vec::new();
+        for event in &self.events {
+            match event {
+                Event::ExpenseRecorded { transaction }
+                | Event::RepaymentRecorded { transaction } => open.push(transaction.clone()),
+                Event::ExpenseEdited { index, transaction } => {
+                    if let Some(old) = open.get_mut(*index) {
+                        *old = Transaction {
+                            id: old.id.clone(),
+                            ..transaction.clone()
+                        };
+                    }
+                }
+                Event::ExpenseRemoved { index } if *index < open.len() => {
+                    open.remove(*index);
+                }
+                Event::SettledUp => settled.append(&mut open),
+                _ => {}
+            }
+        }
+        settled.append(&mut open);
+        settled
+    }
+}
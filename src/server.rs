@@ -0,0 +1,336 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::storage::Storage;
+use crate::{Transaction, TransactionBuilder, Users};
+
+/// Serves a group over HTTP until the process is stopped.
+///
+/// Every request needs `Authorization: Bearer <token>`. Requests are
+/// handled one at a time and every change is saved before it is answered,
+/// so concurrent writers cannot interleave. Responses carry the group's
+/// version, the number of events in its log, as `ETag`; a write sent with
+/// `If-Match` is refused with 412 if the group changed since.
+///
+/// Endpoints, all JSON:
+///
+/// - `GET /members`, `POST /members` `{"name"}`, `DELETE /members/<name>`
+/// - `GET /transactions`, `POST /transactions`, `GET`, `PUT` and `DELETE
+///   /transactions/<id>`, a transaction being `{"payer", "amount",
+///   "description", "category", "date", "weights": {<name>: <weight>}}` or
+///   with `"amounts": {<name>: <amount>}` instead of weights
+/// - `POST /repayments` `{"from", "to", "amount", "date"}`
+/// - `GET /balances`
+/// - `GET /settlement`, and `POST /settlement` to settle up
+pub fn serve(
+    storage: Box<dyn Storage>,
+    users: Users,
+    address: &str,
+    token: &str,
+) -> Result<(), String> {
+    Server::bind(storage, users, address, token)?.run()
+}
+
+/// The server of `serve`, bound but not answering yet.
+pub struct Server {
+    server: tiny_http::Server,
+    api: Api,
+}
+
+impl Server {
+    /// Listens on `address`, port 0 picking a free port.
+    pub fn bind(
+        storage: Box<dyn Storage>,
+        users: Users,
+        address: &str,
+        token: &str,
+    ) -> Result<Server, String> {
+        let server = tiny_http::Server::http(address).map_err(|e| format!("{}: {}", address, e))?;
+        Ok(Server {
+            server,
+            api: Api {
+                storage,
+                users,
+                token: token.to_string(),
+            },
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Answers requests until the process is stopped.
+    pub fn run(mut self) -> Result<(), String> {
+        for mut request in self.server.incoming_requests() {
+            let reply = self.api.handle(&mut request);
+            // Entity tags are quoted.
+            let version = format!("\"{}\"", self.api.users.events().len());
+            let response = Response::from_string(reply.body)
+                .with_status_code(reply.status)
+                .with_header(header("Content-Type", "application/json"))
+                .with_header(header("ETag", &version));
+            let _ = request.respond(response);
+        }
+        Ok(())
+    }
+}
+
+struct Api {
+    storage: Box<dyn Storage>,
+    users: Users,
+    token: String,
+}
+
+struct Reply {
+    status: u16,
+    body: String,
+}
+
+/// A member with their totals.
+#[derive(Serialize)]
+struct Member<'a> {
+    name: &'a str,
+    paid: f64,
+    balance: f64,
+}
+
+/// A transaction as sent by a client. Without weights or amounts,
+/// everyone takes part equally.
+#[derive(Deserialize)]
+struct NewTransaction {
+    payer: String,
+    amount: f64,
+    #[serde(default)]
+    description: String,
+    category: Option<String>,
+    date: Option<String>,
+    #[serde(default)]
+    weights: std::collections::BTreeMap<String, u8>,
+    #[serde(default)]
+    amounts: std::collections::BTreeMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+struct NewMember {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct NewRepayment {
+    from: String,
+    to: String,
+    amount: f64,
+    date: Option<String>,
+}
+
+impl Api {
+    fn handle(&mut self, request: &mut Request) -> Reply {
+        let authorized = header_value(request, "Authorization")
+            .and_then(|v| v.strip_prefix("Bearer ").map(str::to_string))
+            .is_some_and(|token| same(&token, &self.token));
+        if !authorized {
+            return error(401, "Missing or wrong token");
+        }
+
+        let method = request.method().clone();
+        let path = request.url().split('?').next().unwrap_or("").to_string();
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let mut body = String::new();
+        if request.as_reader().read_to_string(&mut body).is_err() {
+            return error(400, "The body is not UTF-8");
+        }
+
+        if method == Method::Get {
+            return self.read(&segments);
+        }
+        let version = self.users.events().len().to_string();
+        if let Some(expected) = header_value(request, "If-Match")
+            && expected.trim_matches('"') != version
+        {
+            return error(412, &format!("The group is at version {} by now", version));
+        }
+        let before = self.users.clone();
+        let reply = self.write(&method, &segments, &body);
+        if reply.status < 300
+            && let Err(e) = self.storage.save(&self.users)
+        {
+            self.users = before;
+            return error(500, &format!("Saving failed: {}", e));
+        }
+        if reply.status >= 300 {
+            self.users = before;
+        }
+        reply
+    }
+
+    fn read(&self, segments: &[&str]) -> Reply {
+        match segments {
+            ["members"] => ok(200, self.members()),
+            ["transactions"] => ok(200, json!(self.users.transactions)),
+            ["transactions", id] => match self.users.transactions.iter().find(|t| t.id == *id) {
+                Some(t) => ok(200, json!(t)),
+                None => error(404, &format!("There is no transaction {}", id)),
+            },
+            ["balances"] => ok(
+                200,
+                self.users
+                    .balances()
+                    .into_iter()
+                    .map(|(name, balance)| json!({ "name": name, "balance": balance }))
+                    .collect(),
+            ),
+            ["settlement"] => ok(200, json!(self.users.settlement())),
+            _ => error(404, "Not found"),
+        }
+    }
+
+    fn write(&mut self, method: &Method, segments: &[&str], body: &str) -> Reply {
+        let result = match (method, segments) {
+            (Method::Post, ["members"]) => parse::<NewMember>(body)
+                .and_then(|m| self.users.add_member(&m.name))
+                .map(|()| (201, self.members())),
+            (Method::Delete, ["members", name]) => self
+                .users
+                .remove_member(name)
+                .map(|()| (200, self.members())),
+            (Method::Post, ["transactions"]) => parse::<NewTransaction>(body)
+                .and_then(|t| self.transaction(t))
+                .and_then(|t| self.users.add_transaction(t))
+                .map(|()| (201, json!(self.users.transactions.last()))),
+            (Method::Put | Method::Delete, ["transactions", id]) => {
+                let Some(index) = self.users.transactions.iter().position(|t| t.id == *id) else {
+                    return error(404, &format!("There is no transaction {}", id));
+                };
+                if *method == Method::Delete {
+                    self.users
+                        .remove_transaction(index)
+                        .map(|()| (200, json!(self.users.transactions)))
+                } else {
+                    parse::<NewTransaction>(body)
+                        .and_then(|t| self.transaction(t))
+                        .and_then(|t| self.users.edit_transaction(index, t))
+                        .map(|()| (200, json!(self.users.transactions[index])))
+                }
+            }
+            (Method::Post, ["repayments"]) => parse::<NewRepayment>(body)
+                .and_then(|r| {
+                    self.users
+                        .record_repayment(&r.from, &r.to, r.amount, r.date)
+                })
+                .map(|()| (201, json!(self.users.transactions.last()))),
+            (Method::Post, ["settlement"]) => {
+                self.users.settle_up();
+                Ok((200, json!(self.users.settlement())))
+            }
+            _ => return error(404, "Not found"),
+        };
+        match result {
+            Ok((status, body)) => ok(status, body),
+            Err(e) => error(400, &e),
+        }
+    }
+
+    fn members(&self) -> Value {
+        let members: Vec<Member> = self
+            .users
+            .users
+            .iter()
+            .zip(self.users.balances())
+            .map(|(u, (_, balance))| Member {
+                name: &u.name,
+                paid: u.amount_paid,
+                balance,
+            })
+            .collect();
+        json!(members)
+    }
+
+    /// Turns a transaction sent by a client into one over all members.
+    fn transaction(&self, new: NewTransaction) -> Result<Transaction, String> {
+        let mut builder = TransactionBuilder::new(&self.users, &new.payer, new.amount)?
+            .with_description(&new.description);
+        if let Some(category) = &new.category {
+            builder = builder.with_category(category);
+        }
+        if let Some(date) = &new.date {
+            builder = builder.with_date(date)?;
+        }
+        for (name, weight) in &new.weights {
+            builder = builder.with_weight(name, *weight)?;
+        }
+        for (name, amount) in &new.amounts {
+            builder = builder.with_amount(name, *amount)?;
+        }
+        builder.build()
+    }
+}
+
+fn parse<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, String> {
+    serde_json::from_str(body).map_err(|e| format!("Invalid request: {}", e))
+}
+
+fn ok(status: u16, body: Value) -> Reply {
+    Reply {
+        status,
+        body: body.to_string(),
+    }
+}
+
+fn error(status: u16, message: &str) -> Reply {
+    ok(status, json!({ "error": message }))
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn header_value(request: &Request, field: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(field))
+        .map(|h| h.value.to_string())
+}
+
+/// Compares tokens in constant time.
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Decodes `%20` and friends in a path segment.
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,715 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, params};
+
+use crate::crypto::{self, Key};
+use crate::{Participant, Transaction, Transfer, User, Users};
+
+/// Where a group is kept. Every read and write of a group goes through it.
+pub trait Storage {
+    fn exists(&self) -> bool;
+    /// The group, its state replayed from the event log and validated.
+    fn load(&self) -> Result<Users, String>;
+    /// The group as stored, without replaying the log.
+    fn load_stored(&self) -> Result<Users, String>;
+    fn save(&self, users: &Users) -> Result<(), String>;
+}
+
+/// Picks the storage for a path: SQLite for `.db`, `.sqlite` and
+/// `.sqlite3` files, the JSON group file otherwise.
+pub fn open(path: &str) -> Box<dyn Storage> {
+    if is_sqlite(path) {
+        Box::new(Sqlite::new(path))
+    } else {
+        Box::new(JsonFile::new(path))
+    }
+}
+
+pub fn is_sqlite(path: &str) -> bool {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    matches!(extension, "db" | "sqlite" | "sqlite3")
+}
+
+/// Whether the group file at `path` is encrypted and needs a passphrase.
+pub fn is_encrypted(path: &str) -> bool {
+    !is_sqlite(path) && fs::read_to_string(path).is_ok_and(|data| crypto::is_encrypted(&data))
+}
+
+/// Opens an encrypted group file, failing right away if the passphrase is
+/// wrong. The group stays encrypted with the same key when saved.
+pub fn unlock(path: &str, passphrase: &str) -> Result<Box<dyn Storage>, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let key = Key::for_file(passphrase, &data).map_err(|e| format!("{}: {}", path, e))?;
+    key.decrypt(&data).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(Box::new(JsonFile::new(path).with_key(key)))
+}
+
+/// Copies a group from one storage to another, e.g. from a JSON file to
+/// SQLite. The target must not exist yet.
+pub fn migrate(from: &dyn Storage, to: &dyn Storage) -> Result<(), String> {
+    if to.exists() {
+        return Err("The target already exists".to_string());
+    }
+    to.save(&from.load()?)
+}
+
+/// The JSON group file, see `format` for what is in it. With a key, the
+/// file is encrypted, see `crypto`.
+pub struct JsonFile {
+    path: String,
+    key: Option<Key>,
+}
+
+impl JsonFile {
+    pub fn new(path: &str) -> Self {
+        JsonFile {
+            path: path.to_string(),
+            key: None,
+        }
+    }
+
+    /// Encrypts the group file with `key` when saving.
+    pub fn with_key(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// The group file JSON, decrypted if need be.
+    fn read(&self) -> Result<String, String> {
+        let data = fs::read_to_string(&self.path).map_err(|e| format!("{}: {}", self.path, e))?;
+        if !crypto::is_encrypted(&data) {
+            return Ok(data);
+        }
+        match &self.key {
+            Some(key) => key
+                .decrypt(&data)
+                .map_err(|e| format!("{}: {}", self.path, e)),
+            None => Err(format!(
+                "{} is encrypted, a passphrase is needed",
+                self.path
+            )),
+        }
+    }
+}
+
+impl Storage for JsonFile {
+    fn exists(&self) -> bool {
+        Path::new(&self.path).exists()
+    }
+
+    fn load(&self) -> Result<Users, String> {
+        crate::format::from_json(&self.read()?).map_err(|e| format!("{}: {}", self.path, e))
+    }
+
+    fn load_stored(&self) -> Result<Users, String> {
+        crate::format::read(&self.read()?).map_err(|e| format!("{}: {}", self.path, e))
+    }
+
+    fn save(&self, users: &Users) -> Result<(), String> {
+        let json = crate::format::to_json(users)?;
+        match &self.key {
+            Some(key) => write(&key.encrypt(&json)?, &self.path),
+            None => write(&json, &self.path),
+        }
+    }
+}
+
+/// An embedded SQLite database with an append-only `events` table for the
+/// log and a table each for the members, transactions, participants and
+/// settlement it replays to. The snapshot, presets, import profiles,
+/// accounting settings and budgets are kept as JSON in the `meta` table.
+pub struct Sqlite {
+    path: String,
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS members (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        amount_paid REAL NOT NULL,
+        net_balance REAL NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        id INTEGER PRIMARY KEY,
+        uid TEXT NOT NULL DEFAULT '',
+        amount REAL NOT NULL,
+        payer TEXT NOT NULL,
+        description TEXT NOT NULL,
+        category TEXT,
+        date TEXT,
+        repayment INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS participants (
+        transaction_id INTEGER NOT NULL REFERENCES transactions (id),
+        name TEXT NOT NULL,
+        weight INTEGER NOT NULL,
+        fair_share REAL,
+        PRIMARY KEY (transaction_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS settlements (
+        id INTEGER PRIMARY KEY,
+        debtor TEXT NOT NULL,
+        creditor TEXT NOT NULL,
+        amount REAL NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        seq INTEGER PRIMARY KEY,
+        event TEXT NOT NULL
+    );
+";
+
+impl Sqlite {
+    pub fn new(path: &str) -> Self {
+        Sqlite {
+            path: path.to_string(),
+        }
+    }
+
+    fn connect(&self) -> Result<Connection, String> {
+        let connection = Connection::open(&self.path).map_err(|e| self.error(e))?;
+        connection
+            .execute_batch(SQLITE_SCHEMA)
+            .map_err(|e| self.error(e))?;
+        // Databases from before transactions had an id.
+        if connection
+            .prepare("SELECT uid FROM transactions LIMIT 0")
+            .is_err()
+        {
+            connection
+                .execute_batch("ALTER TABLE transactions ADD COLUMN uid TEXT NOT NULL DEFAULT ''")
+                .map_err(|e| self.error(e))?;
+        }
+        Ok(connection)
+    }
+
+    fn error(&self, e: rusqlite::Error) -> String {
+        format!("{}: {}", self.path, e)
+    }
+
+    /// The rows of the members table with their ids, in order.
+    fn members(&self, db: &Connection) -> Result<Vec<(i64, User)>, String> {
+        let error = |e: rusqlite::Error| self.error(e);
+        db.prepare("SELECT id, name, amount_paid, net_balance FROM members ORDER BY id")
+            .and_then(|mut query| {
+                query
+                    .query_map([], |row| {
+                        Ok((
+                            row.get(0)?,
+                            User {
+                                name: row.get(1)?,
+                                amount_paid: row.get(2)?,
+                                net_balance: row.get(3)?,
+                            },
+                        ))
+                    })?
+                    .collect()
+            })
+            .map_err(error)
+    }
+
+    /// The rows of the transactions table with their ids and participants,
+    /// in order.
+    fn transactions(&self, db: &Connection) -> Result<Vec<(i64, Transaction)>, String> {
+        let error = |e: rusqlite::Error| self.error(e);
+        let mut participants = db
+            .prepare(
+                "SELECT name, weight, fair_share FROM participants
+                 WHERE transaction_id = ?1 ORDER BY rowid",
+            )
+            .map_err(error)?;
+        let mut transactions = db
+            .prepare(
+                "SELECT id, amount, payer, description, category, date, repayment, uid
+                 FROM transactions ORDER BY id",
+            )
+            .map_err(error)?;
+        let mut rows = transactions
+            .query_map([], |row| {
+                let mut transaction = Transaction::new(row.get(1)?, row.get(2)?, Vec::new())
+                    .with_description(&row.get::<_, String>(3)?)
+                    .with_category(row.get(4)?)
+                    .with_date(row.get(5)?);
+                transaction.repayment = row.get(6)?;
+                transaction.id = row.get(7)?;
+                Ok((row.get::<_, i64>(0)?, transaction))
+            })
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(error)?;
+        for (id, transaction) in &mut rows {
+            transaction.participants = participants
+                .query_map([*id], |row| {
+                    Ok(Participant {
+                        name: row.get(0)?,
+                        weight: row.get(1)?,
+                        fair_share: row.get(2)?,
+                    })
+                })
+                .and_then(|rows| rows.collect())
+                .map_err(error)?;
+        }
+        Ok(rows)
+    }
+
+    /// Writes a transaction and its participants, as row `id` if given.
+    fn insert_transaction(
+        &self,
+        db: &Connection,
+        id: Option<i64>,
+        t: &Transaction,
+    ) -> Result<(), String> {
+        let error = |e: rusqlite::Error| self.error(e);
+        db.execute(
+            "INSERT INTO transactions (id, uid, amount, payer, description, category, date, repayment)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![id, t.id, t.amount, t.payer, t.description, t.category, t.date, t.repayment],
+        )
+        .map_err(error)?;
+        let id = db.last_insert_rowid();
+        for p in &t.participants {
+            db.execute(
+                "INSERT INTO participants (transaction_id, name, weight, fair_share)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![id, p.name, p.weight, p.fair_share],
+            )
+            .map_err(error)?;
+        }
+        Ok(())
+    }
+
+    fn delete_transaction(&self, db: &Connection, id: i64) -> Result<(), String> {
+        db.execute(
+            "DELETE FROM participants WHERE transaction_id = ?1",
+            params![id],
+        )
+        .and_then(|_| db.execute("DELETE FROM transactions WHERE id = ?1", params![id]))
+        .map(|_| ())
+        .map_err(|e| self.error(e))
+    }
+}
+
+impl Storage for Sqlite {
+    fn exists(&self) -> bool {
+        Path::new(&self.path).exists()
+    }
+
+    fn load(&self) -> Result<Users, String> {
+        let mut users = self.load_stored()?;
+        users
+            .rebuild()
+            .map_err(|e| format!("{}: invalid event log: {}", self.path, e))?;
+        users.validate().map_err(|errors| {
+            format!(
+                "{}: invalid group:\n  - {}",
+                self.path,
+                errors.join("\n  - ")
+            )
+        })?;
+        Ok(users)
+    }
+
+    fn load_stored(&self) -> Result<Users, String> {
+        let connection = self.connect()?;
+        let error = |e: rusqlite::Error| self.error(e);
+        let mut users = Users::new();
+
+        users.users = self
+            .members(&connection)?
+            .into_iter()
+            .map(|(_, user)| user)
+            .collect();
+        users.transactions = self
+            .transactions(&connection)?
+            .into_iter()
+            .map(|(_, transaction)| transaction)
+            .collect();
+
+        let meta = |key: &str| -> Result<Option<String>, String> {
+            match connection.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            }) {
+                Ok(value) => Ok(Some(value)),
+                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+                Err(e) => Err(error(e)),
+            }
+        };
+        let json_error = |e: serde_json::Error| format!("{}: {}", self.path, e);
+        if let Some(version) = meta("version")?
+            && version
+                .parse::<u64>()
+                .map_or(true, |v| v > crate::format::CURRENT_VERSION)
+        {
+            return Err(format!(
+                "{}: written by a newer version of dangi-dongi (format {})",
+                self.path, version
+            ));
+        }
+        if let Some(presets) = meta("presets")? {
+            users.presets = serde_json::from_str(&presets).map_err(json_error)?;
+        }
+        if let Some(profiles) = meta("import_profiles")? {
+            users.import_profiles = serde_json::from_str(&profiles).map_err(json_error)?;
+        }
+        if let Some(accounting) = meta("accounting")? {
+            users.accounting = serde_json::from_str(&accounting).map_err(json_error)?;
+        }
+        if let Some(budgets) = meta("budgets")? {
+            users.budgets = serde_json::from_str(&budgets).map_err(json_error)?;
+        }
+        if let Some(conflicts) = meta("conflicts")? {
+            users.conflicts = serde_json::from_str(&conflicts).map_err(json_error)?;
+        }
+        if let Some(snapshot) = meta("snapshot")? {
+            users.snapshot = serde_json::from_str(&snapshot).map_err(json_error)?;
+        }
+
+        let mut events = connection
+            .prepare("SELECT event FROM events ORDER BY seq")
+            .map_err(error)?;
+        let events: Vec<String> = events
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(error)?;
+        users.events = events
+            .iter()
+            .map(|event| serde_json::from_str(event))
+            .collect::<Result<_, _>>()
+            .map_err(json_error)?;
+        // Databases from before the event log only have the state.
+        if users.events.is_empty() && users.snapshot.is_none() {
+            users.events = users.history_from_state();
+        }
+        users.assign_ids();
+        Ok(users)
+    }
+
+    /// Appends the new events and updates the rows of the state that
+    /// changed in one database transaction, so it is either fully written
+    /// or not at all. Stored events are never rewritten: a log that does
+    /// not start with them is refused.
+    fn save(&self, users: &Users) -> Result<(), String> {
+        let mut connection = self.connect()?;
+        let error = |e: rusqlite::Error| self.error(e);
+        let db = connection.transaction().map_err(error)?;
+
+        let stored: Vec<String> = db
+            .prepare("SELECT event FROM events ORDER BY seq")
+            .and_then(|mut query| query.query_map([], |row| row.get(0))?.collect())
+            .map_err(error)?;
+        // Stored from before transactions had ids, they get the same ids
+        // loading gave them.
+        let mut logged = Users::new();
+        logged.events = stored
+            .iter()
+            .map(|event| serde_json::from_str(event))
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("{}: invalid event log: {}", self.path, e))?;
+        logged.assign_ids();
+        if !users.events.starts_with(&logged.events) {
+            return Err(format!(
+                "{}: the stored log has events this copy does not, open the group again",
+                self.path
+            ));
+        }
+        for (i, event) in users.events.iter().enumerate().skip(stored.len()) {
+            let event = serde_json::to_string(event).map_err(|e| e.to_string())?;
+            db.execute(
+                "INSERT INTO events (seq, event) VALUES (?1, ?2)",
+                params![i as i64 + 1, event],
+            )
+            .map_err(error)?;
+        }
+
+        // Members are matched by name and transactions by id, so only the
+        // rows that changed are written.
+        let members = self.members(&db)?;
+        for (id, stored) in &members {
+            match users.users.iter().find(|u| u.name == stored.name) {
+                Some(user)
+                    if user.amount_paid != stored.amount_paid
+                        || user.net_balance != stored.net_balance =>
+                {
+                    db.execute(
+                        "UPDATE members SET amount_paid = ?1, net_balance = ?2 WHERE id = ?3",
+                        params![user.amount_paid, user.net_balance, id],
+                    )
+                    .map_err(error)?;
+                }
+                Some(_) => {}
+                None => {
+                    db.execute("DELETE FROM members WHERE id = ?1", params![id])
+                        .map_err(error)?;
+                }
+            }
+        }
+        for user in &users.users {
+            if !members.iter().any(|(_, m)| m.name == user.name) {
+                db.execute(
+                    "INSERT INTO members (name, amount_paid, net_balance) VALUES (?1, ?2, ?3)",
+                    params![user.name, user.amount_paid, user.net_balance],
+                )
+                .map_err(error)?;
+            }
+        }
+
+        // Rows keep their order by id, so the transactions still stored
+        // have to come first and in the same order, or all are rewritten.
+        let stored = self.transactions(&db)?;
+        let kept: Vec<&str> = stored
+            .iter()
+            .map(|(_, t)| t.id.as_str())
+            .filter(|id| !id.is_empty() && users.transactions.iter().any(|t| t.id == *id))
+            .collect();
+        let in_order = users
+            .transactions
+            .iter()
+            .take(kept.len())
+            .map(|t| t.id.as_str())
+            .eq(kept.iter().copied());
+        if in_order {
+            let (old, new) = users.transactions.split_at(kept.len());
+            for (id, t) in &stored {
+                match old.iter().find(|o| !t.id.is_empty() && o.id == t.id) {
+                    Some(o) if o == t => {}
+                    Some(o) => {
+                        self.delete_transaction(&db, *id)?;
+                        self.insert_transaction(&db, Some(*id), o)?;
+                    }
+                    None => self.delete_transaction(&db, *id)?,
+                }
+            }
+            for t in new {
+                self.insert_transaction(&db, None, t)?;
+            }
+        } else {
+            db.execute_batch("DELETE FROM participants; DELETE FROM transactions;")
+                .map_err(error)?;
+            for t in &users.transactions {
+                self.insert_transaction(&db, None, t)?;
+            }
+        }
+
+        let settlement = users.settlement();
+        let stored: Vec<Transfer> = db
+            .prepare("SELECT debtor, creditor, amount FROM settlements ORDER BY id")
+            .and_then(|mut query| {
+                query
+                    .query_map([], |row| {
+                        Ok(Transfer {
+                            from: row.get(0)?,
+                            to: row.get(1)?,
+                            amount: row.get(2)?,
+                        })
+                    })?
+                    .collect()
+            })
+            .map_err(error)?;
+        if stored != settlement {
+            db.execute("DELETE FROM settlements", []).map_err(error)?;
+            for transfer in settlement {
+                db.execute(
+                    "INSERT INTO settlements (debtor, creditor, amount) VALUES (?1, ?2, ?3)",
+                    params![transfer.from, transfer.to, transfer.amount],
+                )
+                .map_err(error)?;
+            }
+        }
+
+        let json_error = |e: serde_json::Error| e.to_string();
+        let meta = [
+            ("version", crate::format::CURRENT_VERSION.to_string()),
+            (
+                "presets",
+                serde_json::to_string(&users.presets).map_err(json_error)?,
+            ),
+            (
+                "import_profiles",
+                serde_json::to_string(&users.import_profiles).map_err(json_error)?,
+            ),
+            (
+                "accounting",
+                serde_json::to_string(&users.accounting).map_err(json_error)?,
+            ),
+            (
+                "budgets",
+                serde_json::to_string(&users.budgets).map_err(json_error)?,
+            ),
+            (
+                "conflicts",
+                serde_json::to_string(&users.conflicts).map_err(json_error)?,
+            ),
+            (
+                "snapshot",
+                serde_json::to_string(&users.snapshot).map_err(json_error)?,
+            ),
+        ];
+        for (key, value) in meta {
+            db.execute(
+                "INSERT INTO meta (key, value) VALUES (?1, ?2)
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value
+                 WHERE value != excluded.value",
+                params![key, value],
+            )
+            .map_err(error)?;
+        }
+
+        db.commit().map_err(error)
+    }
+}
+
+/// Number of backups kept next to a group file, the oldest go first.
+pub const BACKUPS: usize = 5;
+
+/// Exclusive hold on a group file, released when dropped.
+///
+/// The lock is taken on `<file>.lock` rather than the group file itself,
+/// since saving replaces the group file.
+pub struct GroupLock {
+    _file: File,
+}
+
+impl GroupLock {
+    pub fn acquire(file_path: &str) -> Result<GroupLock, String> {
+        let lock_path = format!("{}.lock", file_path);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| format!("{}: {}", lock_path, e))?;
+        match file.try_lock() {
+            Ok(()) => Ok(GroupLock { _file: file }),
+            Err(fs::TryLockError::WouldBlock) => Err(format!(
+                "{} is open in another dangi-dongi, close it first",
+                file_path
+            )),
+            Err(fs::TryLockError::Error(e)) => Err(format!("{}: {}", lock_path, e)),
+        }
+    }
+}
+
+/// Saves a group without ever leaving a half written file behind.
+pub fn save(users: &Users, file_path: &str) -> Result<(), String> {
+    write(&crate::format::to_json(users)?, file_path)
+}
+
+/// Writes a group file atomically.
+///
+/// The contents go to a temporary file in the same directory, are synced
+/// to disk and then renamed over the group file. The file it replaces is
+/// kept as a timestamped backup.
+fn write(contents: &str, file_path: &str) -> Result<(), String> {
+    let path = Path::new(file_path);
+    let error = |e: std::io::Error| format!("{}: {}", file_path, e);
+
+    let tmp_path = sibling(path, &format!(".{}.tmp", std::process::id()));
+    let written = File::create(&tmp_path).and_then(|mut tmp| {
+        tmp.write_all(contents.as_bytes())?;
+        tmp.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(error(e));
+    }
+
+    if path.exists() {
+        backup(path)?;
+    }
+    fs::rename(&tmp_path, path).map_err(error)?;
+
+    // The rename itself is only durable once the directory is synced.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        File::open(dir).and_then(|d| d.sync_all()).map_err(error)?;
+    }
+    Ok(())
+}
+
+/// Backups of a group file, oldest first.
+pub fn backups(file_path: &str) -> Vec<PathBuf> {
+    let path = Path::new(file_path);
+    let prefix = backup_prefix(path);
+    let mut backups: Vec<PathBuf> = fs::read_dir(backup_dir(path))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(&prefix))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Timestamps are zero padded, so names sort by age.
+    backups.sort();
+    backups
+}
+
+/// Copies the group file into `<file>.backups/` and drops the oldest
+/// backups beyond `BACKUPS`.
+fn backup(path: &Path) -> Result<(), String> {
+    let dir = backup_dir(path);
+    let error = |e: std::io::Error| format!("{}: {}", dir.display(), e);
+    fs::create_dir_all(&dir).map_err(error)?;
+
+    let target = dir.join(format!("{}{}.json", backup_prefix(path), timestamp()));
+    fs::copy(path, &target).map_err(error)?;
+
+    let path = path.to_string_lossy();
+    let backups = backups(&path);
+    for old in &backups[..backups.len().saturating_sub(BACKUPS)] {
+        fs::remove_file(old).map_err(error)?;
+    }
+    Ok(())
+}
+
+fn backup_dir(path: &Path) -> PathBuf {
+    sibling(path, ".backups")
+}
+
+fn backup_prefix(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map_or("group".into(), |s| s.to_string_lossy());
+    format!("{}-", stem)
+}
+
+/// `path` with `suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Current UTC time as `YYYYMMDD-HHMMSS-mmm`.
+fn timestamp() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    let date = crate::date_from_days((secs / 86_400) as i64).replace('-', "");
+    let time = secs % 86_400;
+    format!(
+        "{}-{:02}{:02}{:02}-{:03}",
+        date,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60,
+        now.subsec_millis()
+    )
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::Users;
+use crate::crypto::Key;
+use crate::storage::Storage;
+
+/// First line of every sync message, bumped when the protocol changes.
+/// The group follows it, encrypted with the key of the shared secret, so
+/// that only peers knowing the secret can read or send it.
+const PROTOCOL: &str = "dangi-dongi sync 2";
+
+/// Port peers listen on when none is given.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Where `sync listen` waits for peers when no address is given. Other
+/// machines can only connect when a LAN address is given.
+pub fn default_address() -> String {
+    format!("127.0.0.1:{}", DEFAULT_PORT)
+}
+
+/// Largest group a peer is allowed to send.
+const MAX_MESSAGE: u64 = 64 * 1024 * 1024;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a sync with a peer went.
+pub struct SyncReport {
+    /// The group with the changes of both sides.
+    pub users: Users,
+    /// Events the peer had that we did not.
+    pub received: usize,
+    /// Events we had that the peer did not.
+    pub sent: usize,
+    /// Whether our copy of the group changed and needs saving.
+    pub changed: bool,
+}
+
+/// Merges the copy of a group a peer has into ours.
+///
+/// Both copies are merged against the latest state they had in common,
+/// with the changes of the peer appended to our log; our events are never
+/// rewritten. Each side appends the changes of the other, so the logs list
+/// them in a different order but give the same group, and the next sync
+/// has nothing to do.
+pub fn combine(local: &Users, remote: &Users) -> Result<SyncReport, String> {
+    if let (Some(ours), Some(theirs)) = (local.events().first(), remote.events().first())
+        && ours != theirs
+    {
+        return Err("The peer has a different group".to_string());
+    }
+
+    let theirs: HashSet<u64> = remote.fingerprints()?.into_iter().collect();
+    let common = local
+        .fingerprints()?
+        .iter()
+        .rposition(|f| theirs.contains(f))
+        .unwrap_or(0);
+    let base = local.at(common)?;
+    let (users, _) = crate::merge::merge(&base, local, remote)?;
+    let (peer, _) = crate::merge::merge(&base, remote, local)?;
+    Ok(SyncReport {
+        received: users.events().len() - local.events().len(),
+        sent: peer.events().len() - remote.events().len(),
+        changed: crate::format::to_json(&users)? != crate::format::to_json(local)?,
+        users,
+    })
+}
+
+/// Syncs with a peer listening on `address`, `host` alone meaning the
+/// default port. Both sides need the same `key`.
+pub fn connect(local: &Users, address: &str, key: &Key) -> Result<SyncReport, String> {
+    combine(local, &fetch(local, address, key)?)
+}
+
+/// The network half of `connect`: sends our copy of the group to the peer
+/// and returns theirs, without merging it.
+pub fn fetch(local: &Users, address: &str, key: &Key) -> Result<Users, String> {
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    };
+    let target = address
+        .to_socket_addrs()
+        .map_err(|e| format!("{}: {}", address, e))?
+        .next()
+        .ok_or(format!("{}: no such address", address))?;
+    let mut stream = TcpStream::connect_timeout(&target, Duration::from_secs(3))
+        .map_err(|e| format!("{}: {}", address, e))?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+
+    send(&mut stream, local, key)?;
+    stream
+        .shutdown(Shutdown::Write)
+        .map_err(|e| e.to_string())?;
+    receive(&mut stream, key).map_err(|e| format!("{}: {}", address, e))
+}
+
+/// Answers a peer that connected to us: reads its copy of the group,
+/// sends ours as it was before merging, and merges the two.
+pub fn answer(local: &Users, stream: TcpStream, key: &Key) -> Result<SyncReport, String> {
+    combine(local, &respond(local, stream, key)?)
+}
+
+/// The network half of `answer`: returns the copy of the group the peer
+/// sent, without merging it.
+pub fn respond(local: &Users, mut stream: TcpStream, key: &Key) -> Result<Users, String> {
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+    // Nothing is sent to a peer that does not know the secret.
+    let remote = receive(&mut stream, key)?;
+    send(&mut stream, local, key)?;
+    Ok(remote)
+}
+
+/// Waits for peers on `address` and syncs the group with each in turn,
+/// until the process is stopped.
+pub fn listen(
+    storage: Box<dyn Storage>,
+    mut users: Users,
+    address: &str,
+    key: &Key,
+) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|e| format!("{}: {}", address, e))?;
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let peer = stream
+            .peer_addr()
+            .map_or("a peer".to_string(), |a| a.to_string());
+        match answer(&users, stream, key) {
+            Ok(report) => {
+                if report.changed {
+                    storage.save(&report.users)?;
+                    users = report.users;
+                }
+                println!(
+                    "Synced with {}: {} events received, {} sent.",
+                    peer, report.received, report.sent
+                );
+            }
+            Err(e) => eprintln!("Sync with {} failed: {}", peer, e),
+        }
+    }
+    Ok(())
+}
+
+fn send(stream: &mut TcpStream, users: &Users, key: &Key) -> Result<(), String> {
+    let group = key.encrypt(&crate::format::to_json(users)?)?;
+    let message = format!("{}\n{}", PROTOCOL, group);
+    stream
+        .write_all(message.as_bytes())
+        .map_err(|e| format!("Sending failed: {}", e))
+}
+
+fn receive(stream: &mut TcpStream, key: &Key) -> Result<Users, String> {
+    let mut message = String::new();
+    stream
+        .take(MAX_MESSAGE)
+        .read_to_string(&mut message)
+        .map_err(|e| format!("Receiving failed: {}", e))?;
+    match message.split_once('\n') {
+        Some((PROTOCOL, group)) => {
+            let group = key
+                .decrypt(group)
+                .map_err(|_| "The peer does not know the sync secret".to_string())?;
+            crate::format::from_json(&group).map_err(|e| format!("The peer sent {}", e))
+        }
+        Some((other, _)) if other.starts_with("dangi-dongi sync") => {
+            Err(format!("The peer speaks {}, we speak {}", other, PROTOCOL))
+        }
+        // A peer hangs up on messages it cannot decrypt.
+        _ if message.is_empty() => {
+            Err("The peer hung up, check that both sides use the same secret".to_string())
+        }
+        _ => Err("The peer did not answer with a group".to_string()),
+    }
+}
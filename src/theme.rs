@@ -0,0 +1,208 @@
+use std::path::PathBuf;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// The themes that come with the TUI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThemeName {
+    Dark,
+    Light,
+    HighContrast,
+    Monochrome,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 4] = [
+        ThemeName::Dark,
+        ThemeName::Light,
+        ThemeName::HighContrast,
+        ThemeName::Monochrome,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high-contrast",
+            ThemeName::Monochrome => "monochrome",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<ThemeName, String> {
+        ThemeName::ALL
+            .into_iter()
+            .find(|t| t.name() == name.to_lowercase())
+            .ok_or(format!(
+                "Unknown theme: {}, use dark, light, high-contrast or monochrome",
+                name
+            ))
+    }
+}
+
+/// The styles the TUI draws with, by what they mean rather than by colour.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// The app name in the outer frame.
+    pub title: Style,
+    /// The panel being worked in and the item selected in it.
+    pub focus: Style,
+    /// Removing things, errors and failures.
+    pub danger: Style,
+    /// The Dong panel while there are payments to make.
+    pub info: Style,
+    /// Money owed to a member, a working sync.
+    pub positive: Style,
+    /// Money a member owes.
+    pub negative: Style,
+    /// Things of little note, like waiting for peers.
+    pub muted: Style,
+    /// The banner of a budget that is nearly used up.
+    pub warning_banner: Style,
+    /// The banner of a budget that is exceeded.
+    pub alert_banner: Style,
+    /// The bars and lines of the charts, in turn.
+    pub chart: [Color; 6],
+}
+
+/// The config file, `{"theme": "light"}`.
+#[derive(Deserialize, Default)]
+struct Config {
+    theme: Option<String>,
+}
+
+impl Theme {
+    pub fn named(name: ThemeName) -> Theme {
+        let fg = |color: Color| Style::default().fg(color);
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        match name {
+            ThemeName::Dark => Theme {
+                title: fg(Color::Green).add_modifier(Modifier::BOLD),
+                focus: fg(Color::Yellow),
+                danger: fg(Color::Red),
+                info: fg(Color::Cyan),
+                positive: fg(Color::Green),
+                negative: fg(Color::Red),
+                muted: fg(Color::Gray),
+                warning_banner: fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+                alert_banner: fg(Color::Black).bg(Color::Red).add_modifier(Modifier::BOLD),
+                chart: [
+                    Color::Cyan,
+                    Color::Magenta,
+                    Color::Yellow,
+                    Color::Green,
+                    Color::Blue,
+                    Color::Red,
+                ],
+            },
+            // Yellow and cyan wash out on a white background.
+            ThemeName::Light => Theme {
+                title: fg(Color::Green).add_modifier(Modifier::BOLD),
+                focus: fg(Color::Blue),
+                danger: fg(Color::Red),
+                info: fg(Color::Magenta),
+                positive: fg(Color::Green),
+                negative: fg(Color::Red),
+                muted: fg(Color::DarkGray),
+                warning_banner: fg(Color::White)
+                    .bg(Color::Blue)
+                    .add_modifier(Modifier::BOLD),
+                alert_banner: fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+                chart: [
+                    Color::Blue,
+                    Color::Magenta,
+                    Color::Red,
+                    Color::Green,
+                    Color::Cyan,
+                    Color::Black,
+                ],
+            },
+            ThemeName::HighContrast => Theme {
+                title: fg(Color::White).add_modifier(Modifier::BOLD),
+                focus: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+                danger: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+                info: fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+                positive: fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+                negative: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+                muted: fg(Color::White),
+                warning_banner: fg(Color::Black)
+                    .bg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                alert_banner: fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+                chart: [
+                    Color::LightCyan,
+                    Color::LightMagenta,
+                    Color::LightYellow,
+                    Color::LightGreen,
+                    Color::LightBlue,
+                    Color::LightRed,
+                ],
+            },
+            // Bold, underlined and reversed text instead of colours.
+            ThemeName::Monochrome => Theme {
+                title: bold,
+                focus: bold,
+                danger: bold.add_modifier(Modifier::UNDERLINED),
+                info: Style::default(),
+                positive: Style::default(),
+                negative: Style::default(),
+                muted: Style::default().add_modifier(Modifier::DIM),
+                warning_banner: bold.add_modifier(Modifier::REVERSED),
+                alert_banner: bold.add_modifier(Modifier::REVERSED | Modifier::UNDERLINED),
+                chart: [Color::Reset; 6],
+            },
+        }
+    }
+
+    /// `positive` or `negative` by the sign of a balance, cents aside.
+    pub fn balance(&self, value: f64) -> Style {
+        if value > 0.005 {
+            self.positive
+        } else if value < -0.005 {
+            self.negative
+        } else {
+            Style::default()
+        }
+    }
+
+    /// `DANGI_DONGI_CONFIG`, or `dangi-dongi/config.json` in the config
+    /// directory.
+    pub fn config_path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var("DANGI_DONGI_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        Some(crate::config_dir()?.join("config.json"))
+    }
+
+    /// The theme given on the command line, else the one in the config
+    /// file. Without either it is monochrome if `NO_COLOR` is set, and
+    /// dark otherwise.
+    pub fn load(name: Option<&str>) -> Result<Theme, String> {
+        if let Some(name) = name {
+            return Ok(Theme::named(ThemeName::parse(name)?));
+        }
+        let config = match Theme::config_path() {
+            Some(path) if path.exists() => {
+                let data = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                serde_json::from_str::<Config>(&data)
+                    .map_err(|e| format!("{}: invalid config: {}", path.display(), e))?
+            }
+            _ => Config::default(),
+        };
+        let name = match config.theme {
+            Some(name) => ThemeName::parse(&name)?,
+            None if std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty()) => ThemeName::Monochrome,
+            None => ThemeName::Dark,
+        };
+        Ok(Theme::named(name))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::named(ThemeName::Dark)
+    }
+}
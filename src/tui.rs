@@ -1,10 +1,16 @@
 use std::io;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
 
-use crossterm::event::{KeyCode, KeyEventKind};
-use ratatui::layout::{Alignment, Constraint, Direction, Layout};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Position, Rect};
 use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::{Line, Span, Text};
-use ratatui::widgets::{Block, Borders, Padding, Paragraph, Wrap};
+use ratatui::widgets::{
+    Axis, Bar, BarChart, Block, Borders, Cell, Chart, Clear, Dataset, GraphType, Padding,
+    Paragraph, Row, Table, TableState, Tabs, Wrap,
+};
 use ratatui::{DefaultTerminal, Frame};
 
 #[derive(PartialEq)]
@@ -13,17 +19,212 @@ enum InputMode {
     AddingUser,
     AddingTransactionPurpose,
     AddingTransactionAmount,
+    AddingTransactionDate,
     AddingTransactionPayer,
     AddingTransactionEquality,
     RemovingUser,
     RemovingTransaction,
+    SelectingPreset,
+    Exporting,
+    ResolvingConflict,
+    ViewingStats,
+    ViewingCharts,
+    FilteringTransactions,
+    SelectingMember,
+    ViewingMember,
 }
 
-#[derive(PartialEq)]
-enum SplitType {
-    Equal,
-    UnequalByShare,
-    UnequalByAmount,
+use crate::SplitType;
+use crate::crypto::Key;
+use crate::filter::TransactionFilter;
+use crate::input::{TextInput, Validator};
+use crate::keymap::{Action, Keymap};
+use crate::theme::Theme;
+
+/// How often the TUI syncs with its peer on its own.
+const SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long to wait for a key before looking for peers again.
+const POLL: Duration = Duration::from_millis(250);
+
+/// A column of the Transactions table.
+#[derive(Clone, Copy, PartialEq)]
+enum TransactionColumn {
+    Date,
+    Payer,
+    Amount,
+    Description,
+    Participants,
+}
+
+impl TransactionColumn {
+    const ALL: [TransactionColumn; 5] = [
+        TransactionColumn::Date,
+        TransactionColumn::Payer,
+        TransactionColumn::Amount,
+        TransactionColumn::Description,
+        TransactionColumn::Participants,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            TransactionColumn::Date => "Date",
+            TransactionColumn::Payer => "Payer",
+            TransactionColumn::Amount => "Amount",
+            TransactionColumn::Description => "Description",
+            TransactionColumn::Participants => "Participants",
+        }
+    }
+
+    /// The column to sort by after this one, `None` for the recorded order.
+    fn next(&self) -> Option<Self> {
+        let i = TransactionColumn::ALL.iter().position(|c| c == self)?;
+        TransactionColumn::ALL.get(i + 1).copied()
+    }
+}
+
+/// The members with a share of `t`, in the order they were added.
+fn participant_names(t: &crate::Transaction) -> String {
+    t.participants()
+        .filter(|p| p.weight() > 0 || p.share().unwrap_or(0.0) != 0.0)
+        .map(|p| p.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Moves the selection of a table with `count` rows, `page` of them on
+/// screen. Up and Down wrap around, the paging keys stop at the ends.
+fn scroll(table: &mut TableState, count: usize, page: usize, key: KeyCode) {
+    if count == 0 {
+        return;
+    }
+    let page = page.max(1);
+    let next = match (key, table.selected()) {
+        (KeyCode::Up, Some(0) | None) => count - 1,
+        (KeyCode::Up, Some(i)) => i - 1,
+        (KeyCode::Down, Some(i)) => (i + 1) % count,
+        (KeyCode::Down, None) => 0,
+        (KeyCode::PageUp, i) => i.unwrap_or(0).saturating_sub(page),
+        (KeyCode::PageDown, i) => i.map_or(0, |i| i + page).min(count - 1),
+        (KeyCode::Home, _) => 0,
+        (KeyCode::End, _) => count - 1,
+        _ => return,
+    };
+    table.select(Some(next));
+}
+
+/// Below this the TUI only asks for a bigger terminal.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12;
+
+/// Below this the panels are too short to have a column each, and the
+/// Users and Dong panels share one whatever the width.
+const COLUMNS_HEIGHT: u16 = 24;
+
+/// Smallest chart worth drawing, borders included.
+const CHART_WIDTH: u16 = 32;
+const CHART_HEIGHT: u16 = 5;
+
+/// How the panels are laid out, by the size of the terminal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Arrangement {
+    /// Side by side.
+    Columns,
+    /// The Transactions panel beside the Users panel stacked over the
+    /// Dong panel.
+    Stacked,
+    /// One panel at a time under a row of tabs.
+    Tabs,
+}
+
+impl Arrangement {
+    fn for_size(width: u16, height: u16) -> Self {
+        if width < 80 {
+            Arrangement::Tabs
+        } else if width < 120 || height < COLUMNS_HEIGHT {
+            Arrangement::Stacked
+        } else {
+            Arrangement::Columns
+        }
+    }
+}
+
+/// The panels of the main screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Panel {
+    Users,
+    Transactions,
+    Dong,
+}
+
+impl Panel {
+    const ALL: [Panel; 3] = [Panel::Users, Panel::Transactions, Panel::Dong];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Panel::Users => "Users",
+            Panel::Transactions => "Transactions",
+            Panel::Dong => "Dong",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            Panel::Users => Panel::Transactions,
+            Panel::Transactions => Panel::Dong,
+            Panel::Dong => Panel::Users,
+        }
+    }
+}
+
+/// What clicking a spot of the last frame does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Target {
+    Button(Action),
+    /// Leaves the current mode, like Esc.
+    Back,
+    /// A member in the Users panel.
+    User(usize),
+    /// A row of the Transactions table, by its position in the table.
+    Transaction(usize),
+    /// An item of the list being picked from: split types, presets, export
+    /// formats or the versions of a conflict.
+    Choice(usize),
+    /// A row of the member view.
+    MemberEntry(usize),
+    Tab(Panel),
+}
+
+/// The buttons under the panels, shown when their action can be done.
+const BUTTONS: [(Action, &str); 13] = [
+    (Action::AddUser, "Add user"),
+    (Action::AddTransaction, "Add transaction"),
+    (Action::UsePreset, "Preset"),
+    (Action::Remove, "Remove"),
+    (Action::SettleUp, "Settle up"),
+    (Action::Filter, "Filter"),
+    (Action::Sort, "Sort"),
+    (Action::Member, "Member"),
+    (Action::Stats, "Stats"),
+    (Action::Charts, "Charts"),
+    (Action::Export, "Export"),
+    (Action::Help, "Help"),
+    (Action::Quit, "Quit"),
+];
+
+/// A copy of the group a peer sent, or why there is none.
+struct PeerCopy {
+    peer: String,
+    asked: bool, // Whether we connected to the peer, rather than it to us
+    users: Result<crate::Users, String>,
+}
+
+/// Shown in the corner of the TUI when syncing is on.
+enum SyncState {
+    Off,
+    Waiting,
+    Synced(String),
+    Failed,
 }
 
 impl SplitType {
@@ -45,45 +246,272 @@ impl SplitType {
 
 pub struct App {
     exit: bool,
+    file_path: String,
+    storage: Box<dyn crate::storage::Storage>,
     input_mode: InputMode,
-    user_input: String,
-    transaction_purpose_input: String,
-    transaction_amount_input: String,
+    user_input: TextInput,
+    transaction_purpose_input: TextInput,
+    transaction_amount_input: TextInput,
+    transaction_date_input: TextInput,
     users: crate::Users,
     selected_user_idx: usize, // For selecting user in AddingTransactionFrom
-    selected_transaction_idx: usize, // For selecting transaction in RemovingTransaction
+    transaction_table: TableState, // Selected row and scroll of the Transactions table
+    transaction_page: usize,  // Rows the Transactions table showed last
+    transaction_sort: Option<TransactionColumn>, // None for the recorded order
+    sort_descending: bool,
+    transaction_filter: TransactionFilter,
+    filter_query: String, // The query the filter was read from
+    filter_input: TextInput,
+    member_table: TableState, // Scroll of the member view
+    member_page: usize,
+    selected_preset_idx: usize, // For selecting preset in SelectingPreset
+    selected_export_idx: usize, // For selecting format in Exporting
+    overwrite_export: bool,     // Enter was pressed on an existing export
+    active_preset: Option<String>, // Preset being filled in, if any
+    take_theirs: bool,          // Side picked in ResolvingConflict
+    conflict_idx: usize,        // Conflict shown in ResolvingConflict
     split_type: SplitType,
     transaction_history: Vec<String>,
     dong: Vec<String>,
+    member_stats: Vec<crate::reports::MemberStats>, // Paid and used of the open expenses, by member
+    status: String, // Outcome of the last action, shown under the welcome message
+    peer: Option<String>, // Peer to sync with, if any
+    sync_key: Option<Arc<Key>>, // Key of the secret shared with peers
+    shared: Option<Arc<Mutex<crate::Users>>>, // Copy peers syncing with us get, as last saved
+    peer_copies: (mpsc::Sender<PeerCopy>, mpsc::Receiver<PeerCopy>), // Sent by the sync threads
+    syncing: bool,  // Whether a sync with the peer is under way
+    sync_state: SyncState,
+    last_sync: Option<Instant>,
+    keymap: Keymap,
+    show_help: bool, // Help overlay over the current mode
+    theme: Theme,
+    targets: Vec<(Rect, Target)>, // What the mouse can click in the last frame
+    arrangement: Arrangement,     // Layout of the panels, from the terminal size
+    tab: Panel,                   // Panel shown in Normal mode when there are tabs
 }
 
-pub fn start_tui() -> io::Result<()> {
+pub fn start_tui(file_path: &str) -> io::Result<()> {
+    start_tui_with_sync(file_path, None, None, None, Theme::default())
+}
+
+/// Starts the TUI, syncing with `peer` and answering peers connecting to
+/// `listen` while it runs. Peers need the secret `key` is derived from.
+pub fn start_tui_with_sync(
+    file_path: &str,
+    peer: Option<String>,
+    listen: Option<String>,
+    key: Option<Key>,
+    theme: Theme,
+) -> io::Result<()> {
+    // Held until the TUI exits, so the CLI cannot write the file meanwhile.
+    let _lock = crate::storage::GroupLock::acquire(file_path)
+        .map_err(|e| io::Error::new(io::ErrorKind::WouldBlock, e))?;
+    let listener = listen
+        .as_deref()
+        .map(|address| {
+            TcpListener::bind(address)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", address, e)))
+        })
+        .transpose()?;
+    let keymap = Keymap::load().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
     let mut terminal = ratatui::init();
+    // Pasted text comes in as a whole instead of as key presses.
+    let _ = crossterm::execute!(
+        io::stdout(),
+        crossterm::event::EnableBracketedPaste,
+        crossterm::event::EnableMouseCapture
+    );
+    let app_result = open(&mut terminal, file_path, keymap, theme).and_then(|app| match app {
+        Some(mut app) => {
+            app.sync_state = if peer.is_some() || listener.is_some() {
+                SyncState::Waiting
+            } else {
+                SyncState::Off
+            };
+            app.peer = peer;
+            app.sync_key = key.map(Arc::new);
+            if let Some(listener) = listener {
+                app.accept_peers(listener);
+            }
+            app.run(&mut terminal)
+        }
+        None => Ok(()),
+    });
+    let _ = crossterm::execute!(
+        io::stdout(),
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste
+    );
+    ratatui::restore();
+    app_result
+}
+
+/// Opens the group, asking for its passphrase first if it is encrypted.
+/// `None` if the passphrase prompt was left.
+fn open(
+    terminal: &mut DefaultTerminal,
+    file_path: &str,
+    keymap: Keymap,
+    theme: Theme,
+) -> io::Result<Option<App>> {
+    let storage = if crate::storage::is_encrypted(file_path) {
+        match unlock(terminal, &theme, file_path)? {
+            Some(storage) => storage,
+            None => return Ok(None),
+        }
+    } else {
+        crate::storage::open(file_path)
+    };
+    let users = if storage.exists() {
+        storage
+            .load()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+    } else {
+        crate::Users::new()
+    };
+
     let mut app = App {
         exit: false,
+        file_path: file_path.to_string(),
+        storage,
         input_mode: InputMode::Normal,
-        user_input: String::new(),
-        transaction_purpose_input: String::new(),
-        transaction_amount_input: String::new(),
-        users: crate::Users::new(),
+        user_input: TextInput::new(Validator::Name),
+        transaction_purpose_input: TextInput::new(Validator::Text),
+        transaction_amount_input: TextInput::new(Validator::Amount),
+        transaction_date_input: TextInput::new(Validator::Date),
+        users,
         selected_user_idx: 0,
-        selected_transaction_idx: 0,
+        transaction_table: TableState::default(),
+        transaction_page: 0,
+        transaction_sort: None,
+        sort_descending: false,
+        transaction_filter: TransactionFilter::default(),
+        filter_query: String::new(),
+        filter_input: TextInput::new(Validator::Filter),
+        member_table: TableState::default(),
+        member_page: 0,
+        selected_preset_idx: 0,
+        selected_export_idx: 0,
+        overwrite_export: false,
+        active_preset: None,
+        take_theirs: false,
+        conflict_idx: 0,
         split_type: SplitType::Equal,
         transaction_history: Vec::new(),
         dong: Vec::new(),
+        member_stats: Vec::new(),
+        status: String::new(),
+        peer: None,
+        sync_key: None,
+        shared: None,
+        peer_copies: mpsc::channel(),
+        syncing: false,
+        sync_state: SyncState::Off,
+        last_sync: None,
+        keymap,
+        show_help: false,
+        theme,
+        targets: Vec::new(),
+        arrangement: Arrangement::Columns,
+        tab: Panel::Transactions,
     };
+    app.refresh();
+    if !app.users.conflicts().is_empty() {
+        app.status = format!(
+            "{} merge conflicts, press {} to resolve them",
+            app.users.conflicts().len(),
+            app.keymap.label(Action::ResolveConflicts)
+        );
+    }
+    Ok(Some(app))
+}
 
-    let app_result = app.run(&mut terminal);
-    ratatui::restore();
-    app_result
+/// Asks for the passphrase of an encrypted group until it is right.
+fn unlock(
+    terminal: &mut DefaultTerminal,
+    theme: &Theme,
+    file_path: &str,
+) -> io::Result<Option<Box<dyn crate::storage::Storage>>> {
+    // Room up front, so that typing does not leave copies behind.
+    let mut passphrase = zeroize::Zeroizing::new(String::with_capacity(256));
+    let mut status = String::new();
+    loop {
+        terminal.draw(|frame| {
+            draw_unlock(frame, theme, file_path, passphrase.chars().count(), &status)
+        })?;
+        if let crossterm::event::Event::Key(key_event) = crossterm::event::read()?
+            && key_event.kind == KeyEventKind::Press
+        {
+            match key_event.code {
+                KeyCode::Char(c) => passphrase.push(c),
+                KeyCode::Backspace => {
+                    passphrase.pop();
+                }
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Enter => {
+                    status = "Checking the passphrase...".to_string();
+                    terminal.draw(|frame| draw_unlock(frame, theme, file_path, 0, &status))?;
+                    match crate::storage::unlock(file_path, &passphrase) {
+                        Ok(storage) => return Ok(Some(storage)),
+                        Err(e) => status = e,
+                    }
+                    passphrase.clear();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw_unlock(frame: &mut Frame, theme: &Theme, file_path: &str, typed: usize, status: &str) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled(" Dangi-Dongi ", theme.title))
+        .padding(Padding::top(frame.area().height.saturating_sub(8) / 2));
+    let prompt = Paragraph::new(vec![
+        Line::from(Span::styled(
+            format!("{} is encrypted", file_path),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(format!("Passphrase: {}", "*".repeat(typed))),
+        Line::from(""),
+        Line::from(Span::styled(
+            status,
+            Style::default().add_modifier(Modifier::ITALIC),
+        )),
+        Line::from("< press Enter to unlock | Esc to quit >"),
+    ])
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true })
+    .block(block);
+    frame.render_widget(prompt, frame.area());
 }
 
 impl App {
     fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        terminal.draw(|frame| self.draw(frame))?;
         while !self.exit {
-            match crossterm::event::read()? {
-                crossterm::event::Event::Key(key_event) => self.handle_key_event(key_event)?,
-                _ => {}
+            if crossterm::event::poll(POLL)? {
+                match crossterm::event::read()? {
+                    crossterm::event::Event::Key(key_event) => self.handle_key_event(key_event)?,
+                    crossterm::event::Event::Mouse(mouse_event) => {
+                        self.handle_mouse_event(mouse_event)?
+                    }
+                    crossterm::event::Event::Paste(text) => {
+                        if let Some(input) = self.focused_input() {
+                            input.insert(&text);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            self.take_peer_copies();
+            if self.input_mode == InputMode::Normal
+                && self.last_sync.is_none_or(|t| t.elapsed() >= SYNC_INTERVAL)
+            {
+                self.sync();
             }
 
             terminal.draw(|frame| self.draw(frame))?;
@@ -92,113 +520,281 @@ impl App {
         Ok(())
     }
 
-    fn handle_key_event(&mut self, key_event: crossterm::event::KeyEvent) -> io::Result<()> {
-        if key_event.kind == KeyEventKind::Press {
-            match key_event.code {
-                KeyCode::Char('q') => {
-                    if self.input_mode == InputMode::AddingUser {
-                        self.user_input.push('q');
-                    } else if self.input_mode == InputMode::AddingTransactionPurpose {
-                        self.transaction_purpose_input.push('q');
-                    } else {
-                        self.exit = true;
-                    }
-                }
-                KeyCode::Char('u') => {
-                    if self.input_mode == InputMode::AddingUser {
-                        self.user_input.push('u');
-                    } else if self.input_mode == InputMode::AddingTransactionPurpose {
-                        self.transaction_purpose_input.push('u');
-                    }
-                    if self.input_mode == InputMode::Normal && self.transaction_history.is_empty() {
-                        // only allow adding users if no transactions has been recorded
-                        self.input_mode = InputMode::AddingUser;
-                        self.user_input.clear();
-                    }
+    /// Rebuilds the transaction history, the dong and the member totals
+    /// from `users`.
+    fn refresh(&mut self) {
+        self.transaction_history = self.users.transaction_summaries();
+        self.member_stats = self.users.open_report().members;
+        self.dong = if self.transaction_history.is_empty() {
+            Vec::new()
+        } else {
+            self.users.calculate_total_payments().unwrap_or_default()
+        };
+    }
+
+    /// Writes the group to disk and refreshes the derived panels. The
+    /// change goes out to the peer with the next sync.
+    fn save(&mut self) {
+        if let Err(e) = self.storage.save(&self.users) {
+            self.status = format!("Saving failed: {}", e);
+        }
+        self.share();
+        self.refresh();
+        self.last_sync = None;
+    }
+
+    /// Hands the group to the thread answering peers.
+    fn share(&self) {
+        if let Some(shared) = &self.shared {
+            *shared.lock().unwrap_or_else(|e| e.into_inner()) = self.users.clone();
+        }
+    }
+
+    /// Answers peers connecting to `listener` on a thread of its own with
+    /// the shared copy of the group, handing what they sent to the TUI.
+    fn accept_peers(&mut self, listener: TcpListener) {
+        let Some(key) = self.sync_key.clone() else {
+            return;
+        };
+        let shared = Arc::new(Mutex::new(self.users.clone()));
+        self.shared = Some(Arc::clone(&shared));
+        let sender = self.peer_copies.0.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let peer = stream
+                    .peer_addr()
+                    .map_or("a peer".to_string(), |a| a.ip().to_string());
+                let local = shared.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                let users = crate::sync::respond(&local, stream, &key);
+                let copy = PeerCopy {
+                    peer,
+                    asked: false,
+                    users,
+                };
+                if sender.send(copy).is_err() {
+                    break;
                 }
-                KeyCode::Char('r') => {
-                    if self.input_mode == InputMode::Normal && !self.users.list_users().is_empty() {
-                        // Only allow removing users if no transactions has been recorded
-                        // Otherwise, allow removing transactions
-                        if self.transaction_history.is_empty() {
-                            self.input_mode = InputMode::RemovingUser;
-                            self.selected_user_idx = 0;
-                        } else {
-                            self.input_mode = InputMode::RemovingTransaction;
-                            self.selected_transaction_idx = 0;
-                        }
-                    }
-                    if self.input_mode == InputMode::AddingUser {
-                        self.user_input.push('r');
-                    }
-                    if self.input_mode == InputMode::AddingTransactionPurpose {
-                        self.transaction_purpose_input.push('r');
+            }
+        });
+    }
+
+    /// Syncs with the peer on a thread of its own, if there is a peer and
+    /// no sync is under way already.
+    fn sync(&mut self) {
+        let (Some(peer), Some(key)) = (self.peer.clone(), self.sync_key.clone()) else {
+            return;
+        };
+        if self.syncing {
+            return;
+        }
+        self.syncing = true;
+        self.last_sync = Some(Instant::now());
+        let local = self.users.clone();
+        let sender = self.peer_copies.0.clone();
+        std::thread::spawn(move || {
+            let users = crate::sync::fetch(&local, &peer, &key);
+            let _ = sender.send(PeerCopy {
+                peer,
+                asked: true,
+                users,
+            });
+        });
+    }
+
+    /// Merges the copies of the group peers sent since the last look.
+    /// While an input is open they wait in the channel, so the group does
+    /// not change under it.
+    fn take_peer_copies(&mut self) {
+        if self.input_mode != InputMode::Normal {
+            return;
+        }
+        let copies: Vec<PeerCopy> = self.peer_copies.1.try_iter().collect();
+        for copy in copies {
+            if copy.asked {
+                self.syncing = false;
+            }
+            let result = copy
+                .users
+                .and_then(|remote| crate::sync::combine(&self.users, &remote));
+            self.take_sync(&copy.peer, result);
+        }
+    }
+
+    fn take_sync(&mut self, peer: &str, result: Result<crate::sync::SyncReport, String>) {
+        match result {
+            Ok(report) => {
+                if report.changed {
+                    self.users = report.users;
+                    if let Err(e) = self.storage.save(&self.users) {
+                        self.status = format!("Saving failed: {}", e);
                     }
+                    self.share();
+                    self.refresh();
                 }
-                KeyCode::Char('t') => {
-                    if self.input_mode == InputMode::AddingUser {
-                        self.user_input.push('t');
-                    }
-                    if self.input_mode == InputMode::AddingTransactionPurpose {
-                        self.transaction_purpose_input.push('t');
-                    }
-                    if self.input_mode == InputMode::Normal && self.users.list_users().len() > 1 {
-                        self.input_mode = InputMode::AddingTransactionPurpose;
-                        self.transaction_purpose_input.clear();
-                        self.transaction_amount_input.clear();
-                        self.selected_user_idx = 0;
-                    }
+                if report.received > 0 {
+                    self.status = format!("{} changes came in from {}", report.received, peer);
                 }
-                KeyCode::Char('s') => {
-                    if self.input_mode == InputMode::Normal && !self.dong.is_empty() {
-                        self.users.settle_up();
-                        self.transaction_history.clear();
-                        self.dong.clear();
-                    }
-                    if self.input_mode == InputMode::AddingUser {
-                        self.user_input.push('s');
-                    }
-                    if self.input_mode == InputMode::AddingTransactionPurpose {
-                        self.transaction_purpose_input.push('s');
-                    }
+                self.sync_state = SyncState::Synced(peer.to_string());
+            }
+            Err(e) => {
+                self.status = format!("Sync failed: {}", e);
+                self.sync_state = SyncState::Failed;
+            }
+        }
+    }
+
+    fn export(&mut self) {
+        let format = crate::export::ExportFormat::ALL[self.selected_export_idx];
+        let path = std::path::Path::new(&self.file_path).with_extension(format.extension());
+        // An earlier export is only replaced on a second Enter.
+        if path.exists() && !self.overwrite_export {
+            self.overwrite_export = true;
+            self.status = format!(
+                "{} exists, press Enter again to overwrite it",
+                path.display()
+            );
+            return;
+        }
+        self.overwrite_export = false;
+        self.status = match std::fs::write(&path, self.users.export(format)) {
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(e) => format!("Export failed: {}", e),
+        };
+        self.input_mode = InputMode::Normal;
+    }
+
+    fn record_active_preset(&mut self, amount: Option<f64>, payer: Option<String>) {
+        if let Some(name) = self.active_preset.take() {
+            match self.users.record_preset(&name, amount, payer.as_deref()) {
+                Ok(()) => {
+                    self.status = format!("Preset {} recorded", name);
+                    self.save();
                 }
+                Err(e) => self.status = e,
+            }
+        }
+        self.input_mode = InputMode::Normal;
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> io::Result<()> {
+        if key_event.kind == KeyEventKind::Press {
+            if self.show_help {
+                // Any key closes the help.
+                self.show_help = false;
+                return Ok(());
+            }
+            if self.type_into_field(key_event) {
+                return Ok(());
+            }
+            match key_event.code {
                 KeyCode::Esc => {
                     self.input_mode = InputMode::Normal;
                     self.selected_user_idx = 0;
+                    self.active_preset = None;
                 }
                 KeyCode::Enter => {
                     match self.input_mode {
-                        InputMode::AddingUser => {
-                            let name = self.user_input.trim();
-                            if !name.is_empty() {
-                                self.users.add_user(name.to_string());
+                        InputMode::AddingUser if self.user_input.validate() => {
+                            let name = self.user_input.value().trim().to_string();
+                            match self.users.add_member(&name) {
+                                Ok(()) => {
+                                    self.save();
+                                    self.input_mode = InputMode::Normal;
+                                    self.user_input.clear();
+                                }
+                                Err(e) => self.user_input.set_error(e),
                             }
-                            self.input_mode = InputMode::Normal;
-                            self.user_input.clear();
                         }
-                        InputMode::AddingTransactionPurpose => {
-                            if !self.transaction_purpose_input.trim().is_empty() {
-                                self.input_mode = InputMode::AddingTransactionAmount;
-                            }
+                        InputMode::AddingTransactionPurpose
+                            if self.transaction_purpose_input.validate() =>
+                        {
+                            self.input_mode = InputMode::AddingTransactionAmount;
                         }
-                        InputMode::AddingTransactionAmount => {
-                            let transaction = self.transaction_amount_input.trim();
-                            if !transaction.is_empty() {
+                        InputMode::AddingTransactionAmount
+                            if self.transaction_amount_input.validate() =>
+                        {
+                            let amount = self.transaction_amount_input.value().trim();
+                            if self.active_preset.is_none() {
+                                self.input_mode = InputMode::AddingTransactionDate;
+                                self.transaction_date_input.set(&crate::today());
+                            } else {
                                 self.input_mode = InputMode::AddingTransactionPayer;
+                                if let Some(preset) = self
+                                    .active_preset
+                                    .as_ref()
+                                    .and_then(|name| self.users.find_preset(name))
+                                    && preset.payer().is_some()
+                                {
+                                    let amount = amount.parse::<f64>().ok();
+                                    self.record_active_preset(amount, None);
+                                }
                             }
                         }
+                        InputMode::AddingTransactionDate
+                            if self.transaction_date_input.validate() =>
+                        {
+                            self.input_mode = InputMode::AddingTransactionPayer;
+                        }
                         InputMode::AddingTransactionPayer => {
                             let user_list = self.users.list_users();
                             if !user_list.is_empty() && self.selected_user_idx < user_list.len() {
                                 // TODO: Add logic for saving the transaction payer
                             }
                             self.input_mode = InputMode::AddingTransactionEquality;
+                            if self.active_preset.is_some() {
+                                let amount = self
+                                    .transaction_amount_input
+                                    .value()
+                                    .trim()
+                                    .parse::<f64>()
+                                    .ok();
+                                let payer = user_list.get(self.selected_user_idx).cloned();
+                                self.record_active_preset(amount, payer);
+                            }
+                        }
+                        InputMode::Exporting => self.export(),
+                        InputMode::ResolvingConflict => {
+                            let index = self.shown_conflict();
+                            if let Err(e) = self.users.resolve_conflict(index, self.take_theirs) {
+                                self.status = e;
+                            }
+                            self.save();
+                            self.take_theirs = false;
+                            let left = self.users.conflicts().len();
+                            self.conflict_idx = index.min(left.saturating_sub(1));
+                            if left == 0 {
+                                self.status = "All merge conflicts are resolved".to_string();
+                                self.input_mode = InputMode::Normal;
+                            } else {
+                                self.status = format!("{} merge conflicts left", left);
+                            }
+                        }
+                        InputMode::SelectingPreset => {
+                            if let Some(preset) = self.users.presets().get(self.selected_preset_idx)
+                            {
+                                self.active_preset = Some(preset.name().to_string());
+                                self.transaction_amount_input.clear();
+                                self.selected_user_idx = 0;
+                                if preset.amount().is_none() {
+                                    self.input_mode = InputMode::AddingTransactionAmount;
+                                } else if preset.payer().is_none() {
+                                    self.input_mode = InputMode::AddingTransactionPayer;
+                                } else {
+                                    self.record_active_preset(None, None);
+                                }
+                            }
+                        }
+                        InputMode::SelectingMember => {
+                            self.input_mode = InputMode::ViewingMember;
+                            self.member_table = TableState::default();
                         }
                         InputMode::RemovingUser => {
                             let user_list = self.users.list_users();
                             if self.selected_user_idx < user_list.len() {
-                                self.users
-                                    .remove_user(user_list[self.selected_user_idx].clone());
+                                match self.users.remove_member(&user_list[self.selected_user_idx]) {
+                                    Ok(()) => self.save(),
+                                    Err(e) => self.status = e,
+                                }
                             }
                             self.input_mode = InputMode::Normal;
                         }
@@ -206,30 +802,55 @@ impl App {
                             let user_list = self.users.list_users();
                             self.input_mode = InputMode::Normal;
                             if self.split_type == SplitType::Equal {
-                                let amount =
-                                    self.transaction_amount_input.trim().parse::<f64>().unwrap();
-                                self.users.record_payment(
-                                    user_list[self.selected_user_idx].as_str(),
-                                    amount,
-                                );
-                                self.transaction_history.push(format!(
-                                    "{} paid {} for {} (equally split)",
-                                    user_list[self.selected_user_idx],
-                                    amount,
-                                    self.transaction_purpose_input,
-                                ));
-                                self.dong = self.users.calculate_total_payments().unwrap();
+                                let amount = self
+                                    .transaction_amount_input
+                                    .value()
+                                    .trim()
+                                    .parse::<f64>()
+                                    .unwrap();
+                                let payer = user_list[self.selected_user_idx].clone();
+                                let participants = user_list
+                                    .iter()
+                                    .map(|u| crate::Participant::new(u, 1))
+                                    .collect();
+                                let transaction =
+                                    crate::Transaction::new(amount, payer.clone(), participants)
+                                        .with_description(
+                                            self.transaction_purpose_input.value().trim(),
+                                        )
+                                        .with_date(crate::import::parse_date(
+                                            self.transaction_date_input.value(),
+                                            "%Y-%m-%d",
+                                        ));
+                                match self.users.add_transaction(transaction) {
+                                    Ok(()) => {
+                                        self.status =
+                                            format!("{} for user {} added", amount, payer);
+                                        self.save();
+                                    }
+                                    Err(e) => self.status = e,
+                                }
                             } else {
                                 // todo: handle unequal split
                             }
                         }
                         InputMode::RemovingTransaction => {
-                            self.users
-                                .remove_payment_by_index(self.selected_transaction_idx);
-                            self.transaction_history
-                                .remove(self.selected_transaction_idx);
-                            self.selected_transaction_idx = 0;
-                            self.dong = self.users.calculate_total_payments().unwrap();
+                            let index = self.transaction_table.selected().and_then(|i| {
+                                self.visible_transactions().get(i).map(|(index, _)| *index)
+                            });
+                            if let Some(index) = index {
+                                match self.users.remove_transaction(index) {
+                                    Ok(()) => self.save(),
+                                    Err(e) => self.status = e,
+                                }
+                            }
+                            self.input_mode = InputMode::Normal;
+                        }
+                        InputMode::FilteringTransactions if self.filter_input.validate() => {
+                            self.filter_query = self.filter_input.value().trim().to_string();
+                            self.transaction_filter =
+                                TransactionFilter::parse(&self.filter_query).unwrap_or_default();
+                            self.transaction_table.select(None);
                             self.input_mode = InputMode::Normal;
                         }
                         _ => {}
@@ -238,6 +859,7 @@ impl App {
                 KeyCode::Up => {
                     if self.input_mode == InputMode::AddingTransactionPayer
                         || self.input_mode == InputMode::RemovingUser
+                        || self.input_mode == InputMode::SelectingMember
                     {
                         let user_count = self.users.list_users().len();
                         if user_count > 0 {
@@ -247,115 +869,659 @@ impl App {
                                 self.selected_user_idx -= 1;
                             }
                         }
-                    } else if self.input_mode == InputMode::RemovingTransaction {
-                        let transaction_count = self.transaction_history.len();
-                        if transaction_count > 0 {
-                            if self.selected_transaction_idx == 0 {
-                                self.selected_transaction_idx = transaction_count - 1;
+                    } else if self.browsing_transactions() {
+                        self.scroll_transactions(KeyCode::Up);
+                    } else if self.input_mode == InputMode::ViewingMember {
+                        self.scroll_member(KeyCode::Up);
+                    } else if self.input_mode == InputMode::AddingTransactionEquality {
+                        self.split_type = self.split_type.previous();
+                    } else if self.input_mode == InputMode::ResolvingConflict {
+                        self.take_theirs = !self.take_theirs;
+                    } else if self.input_mode == InputMode::Exporting {
+                        let format_count = crate::export::ExportFormat::ALL.len();
+                        if self.selected_export_idx == 0 {
+                            self.selected_export_idx = format_count - 1;
+                        } else {
+                            self.selected_export_idx -= 1;
+                        }
+                        self.overwrite_export = false;
+                    } else if self.input_mode == InputMode::SelectingPreset {
+                        let preset_count = self.users.presets().len();
+                        if preset_count > 0 {
+                            if self.selected_preset_idx == 0 {
+                                self.selected_preset_idx = preset_count - 1;
                             } else {
-                                self.selected_transaction_idx -= 1;
+                                self.selected_preset_idx -= 1;
                             }
                         }
-                    } else if self.input_mode == InputMode::AddingTransactionEquality {
-                        self.split_type = self.split_type.previous();
                     }
                 }
                 KeyCode::Down => {
                     if self.input_mode == InputMode::AddingTransactionPayer
                         || self.input_mode == InputMode::RemovingUser
+                        || self.input_mode == InputMode::SelectingMember
                     {
                         let user_count = self.users.list_users().len();
                         if user_count > 0 {
                             self.selected_user_idx = (self.selected_user_idx + 1) % user_count;
                         }
-                    } else if self.input_mode == InputMode::RemovingTransaction {
-                        let transaction_count = self.transaction_history.len();
-                        if transaction_count > 0 {
-                            self.selected_transaction_idx =
-                                (self.selected_transaction_idx + 1) % transaction_count;
-                        }
+                    } else if self.browsing_transactions() {
+                        self.scroll_transactions(KeyCode::Down);
+                    } else if self.input_mode == InputMode::ViewingMember {
+                        self.scroll_member(KeyCode::Down);
                     } else if self.input_mode == InputMode::AddingTransactionEquality {
                         self.split_type = self.split_type.next();
+                    } else if self.input_mode == InputMode::ResolvingConflict {
+                        self.take_theirs = !self.take_theirs;
+                    } else if self.input_mode == InputMode::Exporting {
+                        let format_count = crate::export::ExportFormat::ALL.len();
+                        self.selected_export_idx = (self.selected_export_idx + 1) % format_count;
+                        self.overwrite_export = false;
+                    } else if self.input_mode == InputMode::SelectingPreset {
+                        let preset_count = self.users.presets().len();
+                        if preset_count > 0 {
+                            self.selected_preset_idx =
+                                (self.selected_preset_idx + 1) % preset_count;
+                        }
                     }
                 }
-                KeyCode::Backspace => match self.input_mode {
-                    InputMode::AddingUser => {
-                        self.user_input.pop();
+                KeyCode::Left | KeyCode::Right
+                    if self.input_mode == InputMode::ResolvingConflict =>
+                {
+                    let count = self.users.conflicts().len().max(1);
+                    let step = if key_event.code == KeyCode::Left {
+                        count - 1
+                    } else {
+                        1
+                    };
+                    self.conflict_idx = (self.shown_conflict() + step) % count;
+                    self.take_theirs = false;
+                }
+                KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End
+                    if self.browsing_transactions() =>
+                {
+                    self.scroll_transactions(key_event.code);
+                }
+                KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home | KeyCode::End
+                    if self.input_mode == InputMode::ViewingMember =>
+                {
+                    self.scroll_member(key_event.code);
+                }
+                _ => {
+                    if let Some(action) = self.keymap.action(&key_event) {
+                        self.perform(action);
                     }
-                    InputMode::AddingTransactionAmount => {
-                        self.transaction_amount_input.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Clicks pick what is under the pointer, the wheel scrolls like the
+    /// arrow keys.
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) -> io::Result<()> {
+        let up = match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if self.show_help {
+                    self.show_help = false;
+                    return Ok(());
+                }
+                let position = Position::new(mouse_event.column, mouse_event.row);
+                let target = self
+                    .targets
+                    .iter()
+                    .find(|(area, _)| area.contains(position))
+                    .map(|(_, target)| *target);
+                if let Some(target) = target {
+                    self.click(target)?;
+                }
+                return Ok(());
+            }
+            MouseEventKind::ScrollUp if !self.show_help => true,
+            MouseEventKind::ScrollDown if !self.show_help => false,
+            _ => return Ok(()),
+        };
+        // One row at a time, without wrapping around at the ends.
+        let key = if up {
+            KeyCode::PageUp
+        } else {
+            KeyCode::PageDown
+        };
+        if self.browsing_transactions() {
+            let count = self.visible_transactions().len();
+            scroll(&mut self.transaction_table, count, 1, key);
+        } else if self.input_mode == InputMode::ViewingMember {
+            let count = self.member_entries();
+            scroll(&mut self.member_table, count, 1, key);
+        } else {
+            let key = if up { KeyCode::Up } else { KeyCode::Down };
+            self.handle_key_event(KeyEvent::from(key))?;
+        }
+        Ok(())
+    }
+
+    /// Selects what was clicked, or chooses it if it was selected already,
+    /// as Enter would.
+    fn click(&mut self, target: Target) -> io::Result<()> {
+        let enter = KeyEvent::from(KeyCode::Enter);
+        match target {
+            Target::Button(action) => self.perform(action),
+            Target::Back => self.handle_key_event(KeyEvent::from(KeyCode::Esc))?,
+            Target::User(i) => match self.input_mode {
+                InputMode::Normal => {
+                    self.selected_user_idx = i;
+                    self.input_mode = InputMode::ViewingMember;
+                    self.member_table = TableState::default();
+                }
+                InputMode::AddingTransactionPayer
+                | InputMode::RemovingUser
+                | InputMode::SelectingMember => {
+                    if self.selected_user_idx == i {
+                        self.handle_key_event(enter)?;
+                    } else {
+                        self.selected_user_idx = i;
                     }
-                    InputMode::AddingTransactionPurpose => {
-                        self.transaction_purpose_input.pop();
+                }
+                _ => {}
+            },
+            Target::Transaction(i) if self.browsing_transactions() => {
+                if self.input_mode == InputMode::RemovingTransaction
+                    && self.transaction_table.selected() == Some(i)
+                {
+                    self.handle_key_event(enter)?;
+                } else {
+                    self.transaction_table.select(Some(i));
+                }
+            }
+            Target::Transaction(_) => {}
+            Target::Choice(i) => {
+                const SPLITS: [SplitType; 3] = [
+                    SplitType::Equal,
+                    SplitType::UnequalByShare,
+                    SplitType::UnequalByAmount,
+                ];
+                let selected = match self.input_mode {
+                    InputMode::AddingTransactionEquality => {
+                        SPLITS.iter().position(|s| *s == self.split_type)
                     }
-                    _ => {}
-                },
-                KeyCode::Char(c) => {
-                    if self.input_mode == InputMode::AddingUser {
-                        self.user_input.push(c);
-                    } else if self.input_mode == InputMode::AddingTransactionAmount {
-                        // Allow only digits and one decimal point
-                        if c.is_ascii_digit() {
-                            self.transaction_amount_input.push(c);
-                        } else if c == '.' && !self.transaction_amount_input.contains('.') {
-                            self.transaction_amount_input.push(c);
-                        }
-                    } else if self.input_mode == InputMode::AddingTransactionPurpose {
-                        self.transaction_purpose_input.push(c);
+                    InputMode::SelectingPreset => Some(self.selected_preset_idx),
+                    InputMode::Exporting => Some(self.selected_export_idx),
+                    InputMode::ResolvingConflict => Some(self.take_theirs as usize),
+                    _ => return Ok(()),
+                };
+                if selected == Some(i) {
+                    return self.handle_key_event(enter);
+                }
+                match self.input_mode {
+                    InputMode::AddingTransactionEquality => self.split_type = SPLITS[i],
+                    InputMode::SelectingPreset => self.selected_preset_idx = i,
+                    InputMode::Exporting => {
+                        self.selected_export_idx = i;
+                        self.overwrite_export = false;
                     }
+                    _ => self.take_theirs = i == 1,
                 }
-                _ => {}
             }
+            Target::MemberEntry(i) => self.member_table.select(Some(i)),
+            Target::Tab(panel) if self.input_mode == InputMode::Normal => self.tab = panel,
+            Target::Tab(_) => {}
         }
         Ok(())
     }
 
-    fn draw(&self, frame: &mut Frame) {
+    /// The conflict being resolved, kept in range as conflicts go away.
+    fn shown_conflict(&self) -> usize {
+        self.conflict_idx
+            .min(self.users.conflicts().len().saturating_sub(1))
+    }
+
+    /// Whether the arrows and paging keys move through the transactions.
+    fn browsing_transactions(&self) -> bool {
+        matches!(
+            self.input_mode,
+            InputMode::Normal | InputMode::RemovingTransaction
+        )
+    }
+
+    /// The transactions the filter lets through with their index, in the
+    /// chosen order.
+    fn visible_transactions(&self) -> Vec<(usize, &crate::Transaction)> {
+        let mut rows: Vec<(usize, &crate::Transaction)> = self
+            .users
+            .transactions()
+            .enumerate()
+            .filter(|(_, t)| self.transaction_filter.matches(t))
+            .collect();
+        if let Some(column) = self.transaction_sort {
+            rows.sort_by(|(_, a), (_, b)| match column {
+                TransactionColumn::Date => a.date().cmp(&b.date()),
+                TransactionColumn::Payer => a.payer().cmp(b.payer()),
+                TransactionColumn::Amount => a.amount().total_cmp(&b.amount()),
+                TransactionColumn::Description => a
+                    .description()
+                    .to_lowercase()
+                    .cmp(&b.description().to_lowercase()),
+                TransactionColumn::Participants => participant_names(a).cmp(&participant_names(b)),
+            });
+        }
+        if self.sort_descending {
+            rows.reverse();
+        }
+        rows
+    }
+
+    fn scroll_transactions(&mut self, key: KeyCode) {
+        let count = self.visible_transactions().len();
+        scroll(
+            &mut self.transaction_table,
+            count,
+            self.transaction_page,
+            key,
+        );
+    }
+
+    fn scroll_member(&mut self, key: KeyCode) {
+        let count = self.member_entries();
+        scroll(&mut self.member_table, count, self.member_page, key);
+    }
+
+    /// How many rows the member view has.
+    fn member_entries(&self) -> usize {
+        self.users
+            .list_users()
+            .get(self.selected_user_idx)
+            .and_then(|name| self.users.member_detail(name))
+            .map_or(0, |detail| detail.entries.len())
+    }
+
+    /// The text field being filled in, if any.
+    fn focused_input(&mut self) -> Option<&mut TextInput> {
+        match self.input_mode {
+            InputMode::AddingUser => Some(&mut self.user_input),
+            InputMode::AddingTransactionPurpose => Some(&mut self.transaction_purpose_input),
+            InputMode::AddingTransactionAmount => Some(&mut self.transaction_amount_input),
+            InputMode::AddingTransactionDate => Some(&mut self.transaction_date_input),
+            InputMode::FilteringTransactions => Some(&mut self.filter_input),
+            _ => None,
+        }
+    }
+
+    /// Sends typed characters and editing keys to the text field being
+    /// filled in, whatever they are bound to. Returns whether the key was
+    /// used.
+    fn type_into_field(&mut self, key_event: KeyEvent) -> bool {
+        self.focused_input()
+            .is_some_and(|input| input.handle_key(key_event))
+    }
+
+    /// Whether `action` does anything in the current mode.
+    fn can(&self, action: Action) -> bool {
+        let users = self.users.list_users().len();
+        match action {
+            Action::Help | Action::Quit => true,
+            Action::Stats => matches!(self.input_mode, InputMode::Normal | InputMode::ViewingStats),
+            Action::Charts => {
+                matches!(
+                    self.input_mode,
+                    InputMode::Normal | InputMode::ViewingCharts
+                )
+            }
+            Action::Member if self.input_mode == InputMode::ViewingMember => true,
+            _ if self.input_mode != InputMode::Normal => false,
+            Action::NextPanel => self.arrangement == Arrangement::Tabs,
+            // only allow adding users if no transactions has been recorded
+            Action::AddUser => self.transaction_history.is_empty(),
+            Action::Remove | Action::Export | Action::Member => users > 0,
+            Action::AddTransaction => users > 1,
+            Action::UsePreset => users > 1 && !self.users.presets().is_empty(),
+            Action::SettleUp => !self.dong.is_empty(),
+            Action::ResolveConflicts => !self.users.conflicts().is_empty(),
+            Action::Sync => self.peer.is_some(),
+            Action::Filter | Action::Sort | Action::ReverseSort => {
+                !self.transaction_history.is_empty()
+            }
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        if !self.can(action) {
+            return;
+        }
+        match action {
+            Action::Quit => self.exit = true,
+            Action::Help => self.show_help = true,
+            Action::AddUser => {
+                self.input_mode = InputMode::AddingUser;
+                self.user_input.clear();
+            }
+            Action::Remove => {
+                // Only allow removing users if no transactions has been recorded
+                // Otherwise, allow removing transactions
+                if self.transaction_history.is_empty() {
+                    self.input_mode = InputMode::RemovingUser;
+                    self.selected_user_idx = 0;
+                } else {
+                    self.input_mode = InputMode::RemovingTransaction;
+                    if self.transaction_table.selected().is_none() {
+                        self.transaction_table.select(Some(0));
+                    }
+                }
+            }
+            Action::AddTransaction => {
+                self.input_mode = InputMode::AddingTransactionPurpose;
+                self.transaction_purpose_input.clear();
+                self.transaction_amount_input.clear();
+                self.selected_user_idx = 0;
+            }
+            Action::UsePreset => {
+                self.input_mode = InputMode::SelectingPreset;
+                self.selected_preset_idx = 0;
+            }
+            Action::SettleUp => {
+                self.users.settle_up();
+                self.save();
+            }
+            Action::Export => {
+                self.input_mode = InputMode::Exporting;
+                self.selected_export_idx = 0;
+                self.overwrite_export = false;
+            }
+            Action::ResolveConflicts => {
+                self.input_mode = InputMode::ResolvingConflict;
+                self.take_theirs = false;
+                self.conflict_idx = 0;
+            }
+            Action::Sync => self.sync(),
+            Action::Filter => {
+                self.input_mode = InputMode::FilteringTransactions;
+                self.filter_input.set(&self.filter_query);
+            }
+            Action::Sort => {
+                self.transaction_sort = match self.transaction_sort {
+                    None => Some(TransactionColumn::Date),
+                    Some(column) => column.next(),
+                };
+            }
+            Action::ReverseSort => self.sort_descending = !self.sort_descending,
+            Action::Member if self.input_mode == InputMode::Normal => {
+                self.input_mode = InputMode::SelectingMember;
+                self.selected_user_idx = 0;
+            }
+            Action::Member => self.input_mode = InputMode::Normal,
+            Action::NextPanel => self.tab = self.tab.next(),
+            Action::Stats if self.input_mode == InputMode::Normal => {
+                self.input_mode = InputMode::ViewingStats
+            }
+            Action::Charts if self.input_mode == InputMode::Normal => {
+                self.input_mode = InputMode::ViewingCharts
+            }
+            Action::Stats | Action::Charts => self.input_mode = InputMode::Normal,
+        }
+    }
+
+    /// The keys that do something in the current mode, with what they do.
+    fn help(&self) -> Vec<(String, String)> {
+        let typing = matches!(
+            self.input_mode,
+            InputMode::AddingUser
+                | InputMode::AddingTransactionPurpose
+                | InputMode::AddingTransactionAmount
+                | InputMode::AddingTransactionDate
+                | InputMode::FilteringTransactions
+        );
+        let mut keys: Vec<(String, String)> = Vec::new();
+        match self.input_mode {
+            _ if typing => {
+                keys.push(("typing".to_string(), "fill in the field".to_string()));
+                keys.push((
+                    "Left / Right / Home / End".to_string(),
+                    "move the cursor".to_string(),
+                ));
+                keys.push((
+                    "Backspace / Delete".to_string(),
+                    "delete a character".to_string(),
+                ));
+                keys.push(("Enter".to_string(), "confirm".to_string()));
+                keys.push(("Esc".to_string(), "cancel".to_string()));
+                if self.input_mode == InputMode::FilteringTransactions {
+                    keys.push((
+                        "payer: with:".to_string(),
+                        "match the payer or a participant".to_string(),
+                    ));
+                    keys.push(("category:".to_string(), "match the category".to_string()));
+                    keys.push((
+                        "from: to:".to_string(),
+                        "match a date range, YYYY-MM-DD".to_string(),
+                    ));
+                }
+            }
+            InputMode::Normal => {
+                keys.push(("Up / Down".to_string(), "select a transaction".to_string()));
+                keys.push((
+                    "PageUp / PageDown / Home / End".to_string(),
+                    "scroll the transactions".to_string(),
+                ));
+            }
+            InputMode::ViewingStats | InputMode::ViewingCharts => {
+                keys.push(("Esc".to_string(), "go back".to_string()));
+            }
+            InputMode::ViewingMember => {
+                keys.push((
+                    "Up / Down / PageUp / PageDown".to_string(),
+                    "scroll the transactions".to_string(),
+                ));
+                keys.push(("Esc".to_string(), "go back".to_string()));
+            }
+            _ => {
+                keys.push(("Up / Down".to_string(), "move the selection".to_string()));
+                keys.push(("Enter".to_string(), "confirm".to_string()));
+                keys.push(("Esc".to_string(), "cancel".to_string()));
+            }
+        }
+        keys.push((
+            "mouse".to_string(),
+            "click to select, again to choose, wheel to scroll".to_string(),
+        ));
+        for action in Action::ALL {
+            // Typing goes to the field, so only the other keys work there.
+            let labels: Vec<String> = self
+                .keymap
+                .keys(action)
+                .iter()
+                .filter(|k| !(typing && k.is_text()))
+                .map(|k| k.label())
+                .collect();
+            if self.can(action) && !labels.is_empty() {
+                keys.push((labels.join(" / "), action.description().to_string()));
+            }
+        }
+        keys
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        self.targets.clear();
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            let notice = vec![
+                Line::from(format!(
+                    "The terminal is {}x{}, it needs to be at least {}x{}.",
+                    area.width, area.height, MIN_WIDTH, MIN_HEIGHT
+                )),
+                Line::from(format!(
+                    "< press {} to quit >",
+                    self.keymap.label(Action::Quit)
+                )),
+            ];
+            frame.render_widget(
+                Paragraph::new(notice)
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .block(Block::default().padding(Padding::top(area.height / 3))),
+                area,
+            );
+            return;
+        }
+        self.draw_panels(frame);
+        if self.show_help {
+            self.draw_help(frame);
+        }
+    }
+
+    fn draw_help(&self, frame: &mut Frame) {
+        let keys = self.help();
+        let key_width = keys
+            .iter()
+            .map(|(k, _)| k.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut lines: Vec<Line> = keys
+            .iter()
+            .map(|(key, what)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<key_width$}  ", key),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(what.clone()),
+                ])
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from("< press any key to close >").alignment(Alignment::Center));
+
+        let area = frame.area();
+        let width = (lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 6).min(area.width);
+        // Narrow terminals wrap the descriptions.
+        let inner_width = width.saturating_sub(4).max(1);
+        let height = lines
+            .iter()
+            .map(|l| (l.width() as u16).div_ceil(inner_width).max(1))
+            .sum::<u16>()
+            .saturating_add(4)
+            .min(area.height);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(Span::styled(
+                " Keys ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .border_style(self.theme.focus)
+            .padding(Padding::uniform(1));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(block),
+            popup,
+        );
+    }
+
+    fn draw_panels(&mut self, frame: &mut Frame) {
         let size = frame.area();
 
         // Draw outer frame
-        let block = Block::default().borders(Borders::ALL).title(Span::styled(
-            " Dangi-Dongi ",
-            Style::default()
-                .fg(Color::Green)
-                .add_modifier(Modifier::BOLD),
-        ));
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(Span::styled(" Dangi-Dongi ", self.theme.title));
+        let sync = match &self.sync_state {
+            SyncState::Off => None,
+            SyncState::Waiting => Some((" waiting for peers ".to_string(), self.theme.muted)),
+            SyncState::Synced(peer) => {
+                Some((format!(" in sync with {} ", peer), self.theme.positive))
+            }
+            SyncState::Failed if self.peer.is_some() => Some((
+                format!(
+                    " sync failed, {} to retry ",
+                    self.keymap.label(Action::Sync)
+                ),
+                self.theme.danger,
+            )),
+            SyncState::Failed => Some((" sync failed ".to_string(), self.theme.danger)),
+        };
+        if let Some((text, style)) = sync {
+            block = block.title(Line::from(Span::styled(text, style)).right_aligned());
+        }
         frame.render_widget(block, size);
 
+        let alerts = self.users.budget_alerts();
+        self.arrangement = Arrangement::for_size(size.width, size.height);
+        // Short terminals go without the welcome line and the empty rows.
+        let compact = size.height < 30;
+
         // Layout: vertical split for welcome and main area
+        let welcome_height = if compact { 1 } else { 2 } + !alerts.is_empty() as u16;
         let vertical_chunks = Layout::default()
             .direction(Direction::Vertical)
-            .horizontal_margin(2)
-            .vertical_margin(2)
+            .horizontal_margin(if compact { 1 } else { 2 })
+            .vertical_margin(if compact { 1 } else { 2 })
             .constraints(
                 [
-                    Constraint::Length(2), // Welcome message height
-                    Constraint::Min(0),    // Main area
+                    Constraint::Length(welcome_height), // Welcome message height
+                    Constraint::Min(0),                 // Main area
+                    Constraint::Length(1),              // Buttons
                 ]
                 .as_ref(),
             )
             .split(size);
 
-        let welcome = Paragraph::new(vec![Line::from(vec![Span::styled(
-            "Welcome to Dangi-Dongi!",
-            Style::default().add_modifier(Modifier::BOLD),
-        )])])
-        .alignment(Alignment::Center)
-        .wrap(Wrap { trim: true });
+        let mut welcome = vec![
+            Line::from(vec![Span::styled(
+                "Welcome to Dangi-Dongi!",
+                Style::default().add_modifier(Modifier::BOLD),
+            )]),
+            Line::from(Span::styled(
+                self.status.as_str(),
+                Style::default().add_modifier(Modifier::ITALIC),
+            )),
+        ];
+        if compact {
+            welcome.remove(0);
+        }
+        // Budget banner, the most used up budget first.
+        if let Some(first) = alerts.first() {
+            let mut text = format!(" Budget alert: {} ", first.summary());
+            if alerts.len() > 1 {
+                text = format!("{}and {} more ", text, alerts.len() - 1);
+            }
+            let style = if first.spent > first.limit {
+                self.theme.alert_banner
+            } else {
+                self.theme.warning_banner
+            };
+            welcome.push(Line::from(Span::styled(text, style)));
+        }
+        let welcome = Paragraph::new(welcome)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
         frame.render_widget(welcome, vertical_chunks[0]);
+        self.draw_buttons(frame, vertical_chunks[2]);
 
-        // Main area: horizontal split for Users and Transactions
-        let main_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(
-                [
-                    Constraint::Percentage(35),
-                    Constraint::Percentage(35),
-                    Constraint::Percentage(30),
-                ]
-                .as_ref(),
-            )
-            .split(vertical_chunks[1]);
+        if self.input_mode == InputMode::ViewingStats {
+            self.draw_stats(frame, vertical_chunks[1]);
+            return;
+        }
+        if self.input_mode == InputMode::ViewingCharts {
+            self.draw_charts(frame, vertical_chunks[1]);
+            return;
+        }
+        if self.input_mode == InputMode::ViewingMember {
+            self.draw_member(frame, vertical_chunks[1]);
+            return;
+        }
+
+        let [users_area, transaction_area, dong_area] =
+            self.draw_arrangement(frame, vertical_chunks[1]);
+        let padding = if compact {
+            Padding::horizontal(1)
+        } else {
+            Padding::uniform(1)
+        };
 
         // For users
         let users_block = {
@@ -365,20 +1531,17 @@ impl App {
                     " Users ",
                     Style::default().add_modifier(Modifier::BOLD),
                 ))
-                .padding(Padding {
-                    left: 1,
-                    right: 1,
-                    top: 1,
-                    bottom: 1,
-                });
+                .padding(padding);
             if matches!(
                 self.input_mode,
-                InputMode::AddingUser | InputMode::AddingTransactionPayer
+                InputMode::AddingUser
+                    | InputMode::AddingTransactionPayer
+                    | InputMode::SelectingMember
             ) {
-                block = block.border_style(Style::default().fg(Color::Yellow));
+                block = block.border_style(self.theme.focus);
             }
             if matches!(self.input_mode, InputMode::RemovingUser) {
-                block = block.border_style(Style::default().fg(Color::Red));
+                block = block.border_style(self.theme.danger);
             }
             block
         };
@@ -387,11 +1550,13 @@ impl App {
 
         let users_content = match self.input_mode {
             InputMode::AddingUser => {
-                lines.push(Line::from(format!("> {}", self.user_input.as_str())));
+                lines.extend(self.user_input.lines("> ", self.theme.danger));
                 let text = Text::from(lines);
-                Paragraph::new(text)
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true })
+                Some(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Left)
+                        .wrap(Wrap { trim: true }),
+                )
             }
             InputMode::RemovingUser => {
                 let mut lines: Vec<Line> = Vec::new();
@@ -399,7 +1564,7 @@ impl App {
                     if i == self.selected_user_idx {
                         lines.push(Line::from(Span::styled(
                             format!("> {} <", u),
-                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                            self.theme.danger.add_modifier(Modifier::BOLD),
                         )));
                     } else {
                         lines.push(Line::from(Span::raw(u)));
@@ -408,142 +1573,200 @@ impl App {
                 lines.push(Line::from("----------"));
                 lines.push(Line::from("< select user to remove >"));
                 let text = Text::from(lines);
-                Paragraph::new(text)
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true })
+                Some(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Left)
+                        .wrap(Wrap { trim: true }),
+                )
             }
-            InputMode::AddingTransactionPayer => {
-                // Highlight the selected user
+            InputMode::SelectingMember => {
                 let mut lines: Vec<Line> = Vec::new();
                 for (i, u) in user_list.iter().enumerate() {
                     if i == self.selected_user_idx {
                         lines.push(Line::from(Span::styled(
                             format!("> {} <", u),
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
                         )));
                     } else {
                         lines.push(Line::from(Span::raw(u)));
                     }
                 }
                 lines.push(Line::from("----------"));
-                lines.push(Line::from("< select payer of the transaction >"));
+                lines.push(Line::from("< select member to show >"));
                 let text = Text::from(lines);
-                Paragraph::new(text)
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true })
+                Some(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Left)
+                        .wrap(Wrap { trim: true }),
+                )
             }
-            InputMode::AddingTransactionEquality => {
+            InputMode::AddingTransactionPayer => {
+                // Highlight the selected user
                 let mut lines: Vec<Line> = Vec::new();
                 for (i, u) in user_list.iter().enumerate() {
                     if i == self.selected_user_idx {
                         lines.push(Line::from(Span::styled(
-                            format!("{}", u),
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
+                            format!("> {} <", u),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
                         )));
                     } else {
                         lines.push(Line::from(Span::raw(u)));
                     }
                 }
+                lines.push(Line::from("----------"));
+                lines.push(Line::from("< select payer of the transaction >"));
                 let text = Text::from(lines);
-                Paragraph::new(text)
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true })
+                Some(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Left)
+                        .wrap(Wrap { trim: true }),
+                )
             }
-            _ => {
-                if self.transaction_history.is_empty() {
-                    if !lines.is_empty() {
-                        lines.push(Line::from("----------"));
-                        lines.push(Line::from("< press 'u' to add user | 'r' to remove user >"));
+            InputMode::AddingTransactionEquality => {
+                let mut lines: Vec<Line> = Vec::new();
+                for (i, u) in user_list.iter().enumerate() {
+                    if i == self.selected_user_idx {
+                        lines.push(Line::from(Span::styled(
+                            u.to_string(),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
+                        )));
                     } else {
-                        lines.push(Line::from("< press 'u' to add user >"));
+                        lines.push(Line::from(Span::raw(u)));
                     }
                 }
                 let text = Text::from(lines);
-                Paragraph::new(text)
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true })
+                Some(
+                    Paragraph::new(text)
+                        .alignment(Alignment::Left)
+                        .wrap(Wrap { trim: true }),
+                )
             }
+            _ => None,
         };
 
-        let users_area = main_chunks[0];
         let users_inner = users_block.inner(users_area);
         frame.render_widget(users_block, users_area);
 
-        frame.render_widget(users_content, users_inner);
+        // The table of the Users panel has a header row, the lists do not.
+        let first_user = users_inner.y + users_content.is_none() as u16;
+        match users_content {
+            Some(content) => frame.render_widget(content, users_inner),
+            None => self.draw_users(frame, users_inner),
+        }
+        for i in 0..user_list.len() as u16 {
+            let row = Rect::new(users_inner.x, first_user + i, users_inner.width, 1);
+            if users_inner.contains(row.as_position()) {
+                self.targets.push((row, Target::User(i as usize)));
+            }
+        }
 
         // For transactions
+        let transactions_title = if self.filter_query.is_empty() {
+            " Transactions ".to_string()
+        } else {
+            format!(
+                " Transactions: {} of {} match ",
+                self.visible_transactions().len(),
+                self.transaction_history.len()
+            )
+        };
         let transactions_block = {
             let mut block = Block::default()
                 .borders(Borders::ALL)
                 .title(Span::styled(
-                    " Transactions ",
+                    transactions_title,
                     Style::default().add_modifier(Modifier::BOLD),
                 ))
-                .padding(Padding {
-                    left: 1,
-                    right: 1,
-                    top: 1,
-                    bottom: 1,
-                });
+                .padding(padding);
             if matches!(
                 self.input_mode,
                 InputMode::AddingTransactionAmount
+                    | InputMode::AddingTransactionDate
                     | InputMode::AddingTransactionEquality
                     | InputMode::AddingTransactionPurpose
+                    | InputMode::SelectingPreset
+                    | InputMode::FilteringTransactions
             ) {
-                block = block.border_style(Style::default().fg(Color::Yellow));
+                block = block.border_style(self.theme.focus);
             }
             if matches!(self.input_mode, InputMode::RemovingTransaction) {
-                block = block.border_style(Style::default().fg(Color::Red));
+                block = block.border_style(self.theme.danger);
             }
             block
         };
 
+        let add = format!(
+            "< press {} to add transaction",
+            self.keymap.label(Action::AddTransaction)
+        );
+        let preset = format!(
+            " | {} to use a preset",
+            self.keymap.label(Action::UsePreset)
+        );
+        let remove = format!(
+            " | {} to remove transaction | {} to filter | {} to sort",
+            self.keymap.label(Action::Remove),
+            self.keymap.label(Action::Filter),
+            self.keymap.label(Action::Sort)
+        );
         let (transaction_default_text, italic) =
-            if self.users.list_users().len() > 1 && self.transaction_history.is_empty() {
-                ("< press 't' to add transaction >", false)
-            } else if self.users.list_users().len() > 1 && self.transaction_history.len() > 0 {
-                (
-                    "< press 't' to add transaction | 'r' to remove transaction >",
-                    false,
-                )
+            if self.users.list_users().len() > 1 && !self.users.presets().is_empty() {
+                if self.transaction_history.is_empty() {
+                    (format!("{}{} >", add, preset), false)
+                } else {
+                    (format!("{}{}{} >", add, preset, remove), false)
+                }
+            } else if self.users.list_users().len() > 1 && self.transaction_history.is_empty() {
+                (format!("{} >", add), false)
+            } else if self.users.list_users().len() > 1 && !self.transaction_history.is_empty() {
+                (format!("{}{} >", add, remove), false)
             } else {
                 (
-                    "Please add at least two users to start recording transactions.",
+                    "Please add at least two users to start recording transactions.".to_string(),
                     true,
                 )
             };
         let transaction_content = match self.input_mode {
-            InputMode::AddingTransactionPurpose => Paragraph::new(Line::from(format!(
-                "> what for: {}",
-                self.transaction_purpose_input.as_str()
-            )))
-            .alignment(Alignment::Left)
-            .wrap(Wrap { trim: true }),
-            InputMode::AddingTransactionAmount => Paragraph::new(Line::from(format!(
-                "> amount: {}",
-                self.transaction_amount_input.as_str()
-            )))
-            .alignment(Alignment::Left)
-            .wrap(Wrap { trim: true }),
-            InputMode::AddingTransactionPayer => {
+            InputMode::AddingTransactionPurpose => Some(
+                Paragraph::new(
+                    self.transaction_purpose_input
+                        .lines("> what for: ", self.theme.danger),
+                )
+                .alignment(Alignment::Left)
+                .wrap(Wrap { trim: true }),
+            ),
+            InputMode::AddingTransactionAmount => Some({
+                let prompt = match &self.active_preset {
+                    Some(preset) => format!("> amount for {}: ", preset),
+                    None => "> amount: ".to_string(),
+                };
+                Paragraph::new(
+                    self.transaction_amount_input
+                        .lines(&prompt, self.theme.danger),
+                )
+                .alignment(Alignment::Left)
+                .wrap(Wrap { trim: true })
+            }),
+            InputMode::AddingTransactionDate => Some(
+                Paragraph::new(
+                    self.transaction_date_input
+                        .lines("> date: ", self.theme.danger),
+                )
+                .alignment(Alignment::Left)
+                .wrap(Wrap { trim: true }),
+            ),
+            InputMode::AddingTransactionPayer => Some(
                 Paragraph::new(Line::from("> payer: (select user from 'Users' panel)"))
                     .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true })
-            }
-            InputMode::AddingTransactionEquality => match self.split_type {
+                    .wrap(Wrap { trim: true }),
+            ),
+            InputMode::AddingTransactionEquality => Some(match self.split_type {
                 // todo: see if I can simplify it
                 SplitType::Equal => {
                     let lines = vec![
                         Line::styled(
                             "> Split equally <",
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
                         ),
                         Line::from(" Split unequally by share"),
                         Line::from(" Split unequally by amount"),
@@ -558,9 +1781,7 @@ impl App {
                         Line::from("Split unequally by share"),
                         Line::styled(
                             "> Split unequally by amount <",
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
                         ),
                     ];
                     Paragraph::new(lines)
@@ -572,9 +1793,7 @@ impl App {
                         Line::from("Split equally"),
                         Line::styled(
                             "> Split unequally by share <",
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::BOLD),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
                         ),
                         Line::from("Split unequally by amount"),
                     ];
@@ -582,52 +1801,44 @@ impl App {
                         .alignment(Alignment::Left)
                         .wrap(Wrap { trim: true })
                 }
-            },
-            InputMode::RemovingTransaction => {
+            }),
+            InputMode::SelectingPreset => Some({
                 let mut lines: Vec<Line> = Vec::new();
-                for (i, u) in self.transaction_history.iter().enumerate() {
-                    if i == self.selected_transaction_idx {
+                for (i, p) in self.users.presets().iter().enumerate() {
+                    if i == self.selected_preset_idx {
                         lines.push(Line::from(Span::styled(
-                            format!("> {} <", u),
-                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                            format!("> {} <", p.name()),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
                         )));
                     } else {
-                        lines.push(Line::from(Span::raw(u)));
+                        lines.push(Line::from(Span::raw(p.name())));
                     }
                 }
                 lines.push(Line::from("----------"));
-                lines.push(Line::from("< select transaction to remove >"));
+                lines.push(Line::from("< select preset >"));
                 let text = Text::from(lines);
                 Paragraph::new(text)
                     .alignment(Alignment::Left)
                     .wrap(Wrap { trim: true })
-            }
-            _ => {
-                let mut lines: Vec<Line> = self
-                    .transaction_history
-                    .iter()
-                    .map(|u| Line::from(Span::raw(u)))
-                    .collect();
-                if !lines.is_empty() {
-                    lines.push(Line::from("----------"));
-                }
-                lines.push(Line::from(transaction_default_text));
-                Paragraph::new(lines)
-                    .add_modifier(if italic {
-                        Modifier::ITALIC
-                    } else {
-                        Modifier::empty()
-                    })
-                    .alignment(Alignment::Left)
-                    .wrap(Wrap { trim: true })
-            }
+            }),
+            _ => None,
         };
 
-        let transaction_area = main_chunks[1];
         let transaction_inner = transactions_block.inner(transaction_area);
 
         frame.render_widget(transactions_block, transaction_area);
-        frame.render_widget(transaction_content, transaction_inner);
+        match transaction_content {
+            Some(content) => frame.render_widget(content, transaction_inner),
+            None => {
+                self.draw_transactions(frame, transaction_inner, transaction_default_text, italic)
+            }
+        }
+        let choices = match self.input_mode {
+            InputMode::AddingTransactionEquality => 3,
+            InputMode::SelectingPreset => self.users.presets().len(),
+            _ => 0,
+        };
+        self.add_choices(transaction_inner, 0, choices);
 
         let dong_block = {
             let mut block = Block::default()
@@ -636,19 +1847,74 @@ impl App {
                     " Dong ",
                     Style::default().add_modifier(Modifier::BOLD),
                 ))
-                .padding(Padding {
-                    left: 1,
-                    right: 1,
-                    top: 1,
-                    bottom: 1,
-                });
+                .padding(padding);
             if !self.dong.is_empty() {
-                block = block.border_style(Style::default().fg(Color::Cyan));
+                block = block.border_style(self.theme.info);
+            }
+            if matches!(
+                self.input_mode,
+                InputMode::Exporting | InputMode::ResolvingConflict
+            ) {
+                block = block.border_style(self.theme.focus);
             }
             block
         };
 
         let dong = match self.dong.is_empty() {
+            _ if self.input_mode == InputMode::Exporting => {
+                let mut lines: Vec<Line> = Vec::new();
+                for (i, f) in crate::export::ExportFormat::ALL.iter().enumerate() {
+                    if i == self.selected_export_idx {
+                        lines.push(Line::from(Span::styled(
+                            format!("> {} <", f.name()),
+                            self.theme.focus.add_modifier(Modifier::BOLD),
+                        )));
+                    } else {
+                        lines.push(Line::from(Span::raw(f.name())));
+                    }
+                }
+                lines.push(Line::from("----------"));
+                lines.push(Line::from(if self.overwrite_export {
+                    "< Enter again to overwrite the earlier export >"
+                } else {
+                    "< select export format >"
+                }));
+                Paragraph::new(lines)
+                    .alignment(Alignment::Left)
+                    .wrap(Wrap { trim: true })
+            }
+            _ if self.input_mode == InputMode::ResolvingConflict => {
+                let mut lines: Vec<Line> = Vec::new();
+                let count = self.users.conflicts().len();
+                if let Some(conflict) = self.users.conflicts().get(self.shown_conflict()) {
+                    lines.push(Line::from(format!(
+                        "Conflict {} of {}:",
+                        self.shown_conflict() + 1,
+                        count
+                    )));
+                    let sides = [("ours", conflict.ours()), ("theirs", conflict.theirs())];
+                    for (i, (side, version)) in sides.into_iter().enumerate() {
+                        let text = format!("{}: {}", side, version);
+                        if (i == 1) == self.take_theirs {
+                            lines.push(Line::from(Span::styled(
+                                format!("> {} <", text),
+                                self.theme.focus.add_modifier(Modifier::BOLD),
+                            )));
+                        } else {
+                            lines.push(Line::from(Span::raw(text)));
+                        }
+                    }
+                }
+                lines.push(Line::from("----------"));
+                lines.push(Line::from(if count > 1 {
+                    "< both sides changed it, select the version to keep, Left and Right for the other conflicts >"
+                } else {
+                    "< both sides changed it, select the version to keep >"
+                }));
+                Paragraph::new(lines)
+                    .alignment(Alignment::Left)
+                    .wrap(Wrap { trim: true })
+            }
             true => Paragraph::new(Line::from("Nothing to see here yet!"))
                 .alignment(Alignment::Left)
                 .add_modifier(Modifier::ITALIC)
@@ -656,7 +1922,14 @@ impl App {
             false => {
                 let other = vec![
                     Line::from("----------"),
-                    Line::from("< press 's' to settle up payments >"),
+                    Line::from(format!(
+                        "< press {} to settle up payments | {} to export | {} for stats | {} for charts | {} for help >",
+                        self.keymap.label(Action::SettleUp),
+                        self.keymap.label(Action::Export),
+                        self.keymap.label(Action::Stats),
+                        self.keymap.label(Action::Charts),
+                        self.keymap.label(Action::Help)
+                    )),
                 ];
                 let lines: Vec<Line> = self
                     .dong
@@ -670,9 +1943,733 @@ impl App {
             }
         };
 
-        let dong_area = main_chunks[2];
         let dong_inner = dong_block.inner(dong_area);
         frame.render_widget(dong_block, dong_area);
         frame.render_widget(dong, dong_inner);
+        match self.input_mode {
+            InputMode::Exporting => {
+                self.add_choices(dong_inner, 0, crate::export::ExportFormat::ALL.len())
+            }
+            // Under "Conflict 1 of 2:".
+            InputMode::ResolvingConflict => self.add_choices(dong_inner, 1, 2),
+            _ => {}
+        }
+    }
+
+    /// Where the Users, Transactions and Dong panels go. A panel that is
+    /// not shown gets an empty area. With tabs, the tab row is drawn too.
+    fn draw_arrangement(&mut self, frame: &mut Frame, area: Rect) -> [Rect; 3] {
+        match self.arrangement {
+            Arrangement::Columns => Layout::horizontal([
+                Constraint::Percentage(35),
+                Constraint::Percentage(40),
+                Constraint::Percentage(25),
+            ])
+            .areas(area),
+            Arrangement::Stacked => {
+                let [side, transactions] =
+                    Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                        .areas(area);
+                let [users, dong] =
+                    Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .areas(side);
+                [users, transactions, dong]
+            }
+            Arrangement::Tabs => {
+                let [tabs_area, panel_area] =
+                    Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+                let shown = self.shown_panel();
+                frame.render_widget(
+                    Tabs::new(Panel::ALL.map(|p| p.title()))
+                        .select(Panel::ALL.iter().position(|p| *p == shown))
+                        .highlight_style(self.theme.focus.add_modifier(Modifier::REVERSED)),
+                    tabs_area,
+                );
+                // Each title is padded by a space on both sides and followed
+                // by a divider.
+                let mut x = tabs_area.x;
+                for panel in Panel::ALL {
+                    let width = panel.title().len() as u16 + 2;
+                    let tab = Rect::new(x, tabs_area.y, width, 1).intersection(tabs_area);
+                    self.targets.push((tab, Target::Tab(panel)));
+                    x += width + 1;
+                }
+                Panel::ALL.map(|p| {
+                    if p == shown {
+                        panel_area
+                    } else {
+                        Rect::default()
+                    }
+                })
+            }
+        }
+    }
+
+    /// The panel the current mode works in, else the chosen tab.
+    fn shown_panel(&self) -> Panel {
+        match self.input_mode {
+            InputMode::AddingUser
+            | InputMode::RemovingUser
+            | InputMode::AddingTransactionPayer
+            | InputMode::SelectingMember => Panel::Users,
+            InputMode::AddingTransactionPurpose
+            | InputMode::AddingTransactionAmount
+            | InputMode::AddingTransactionDate
+            | InputMode::AddingTransactionEquality
+            | InputMode::RemovingTransaction
+            | InputMode::SelectingPreset
+            | InputMode::FilteringTransactions => Panel::Transactions,
+            InputMode::Exporting | InputMode::ResolvingConflict => Panel::Dong,
+            _ => self.tab,
+        }
+    }
+
+    /// Makes `count` lines of `area`, from line `first` on, clickable as
+    /// the items of the list being picked from.
+    fn add_choices(&mut self, area: Rect, first: u16, count: usize) {
+        for i in 0..count as u16 {
+            let row = Rect::new(area.x, area.y + first + i, area.width, 1);
+            if area.contains(row.as_position()) {
+                self.targets.push((row, Target::Choice(i as usize)));
+            }
+        }
+    }
+
+    /// The actions that can be done now as a row of buttons, with a Back
+    /// button outside the normal mode.
+    fn draw_buttons(&mut self, frame: &mut Frame, area: Rect) {
+        let mut buttons: Vec<(String, Target)> = Vec::new();
+        if self.input_mode != InputMode::Normal {
+            buttons.push(("Back".to_string(), Target::Back));
+        }
+        for (action, name) in BUTTONS {
+            if self.can(action) {
+                buttons.push((name.to_string(), Target::Button(action)));
+            }
+        }
+        let mut spans: Vec<Span> = Vec::new();
+        let mut x = area.x;
+        for (name, target) in buttons {
+            let width = name.chars().count() as u16 + 2;
+            if x + width > area.right() {
+                break;
+            }
+            self.targets.push((Rect::new(x, area.y, width, 1), target));
+            spans.push(Span::styled(
+                format!(" {} ", name),
+                Style::default().add_modifier(Modifier::REVERSED),
+            ));
+            spans.push(Span::raw(" "));
+            x += width + 1;
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+
+    /// The members with what they paid, what they used up and their
+    /// balance, and a total row whose balance has to come to zero.
+    fn draw_users(&self, frame: &mut Frame, area: Rect) {
+        let hint = if self.users.members().next().is_none() {
+            format!(
+                "< press {} to add user >",
+                self.keymap.label(Action::AddUser)
+            )
+        } else if self.transaction_history.is_empty() {
+            format!(
+                "< press {} to add user | {} to remove user >",
+                self.keymap.label(Action::AddUser),
+                self.keymap.label(Action::Remove)
+            )
+        } else {
+            format!(
+                "< press {} for member details >",
+                self.keymap.label(Action::Member)
+            )
+        };
+        if self.users.members().next().is_none() {
+            frame.render_widget(Paragraph::new(hint).wrap(Wrap { trim: true }), area);
+            return;
+        }
+        let footer = vec![Line::from("----------"), Line::from(hint)];
+        let footer_height: u16 = footer
+            .iter()
+            .map(|l| (l.width() as u16).div_ceil(area.width.max(1)).max(1))
+            .sum();
+        let [table_area, footer_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(footer_height)]).areas(area);
+
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let number = |value: f64| Cell::from(Line::from(format!("{:.2}", value)).right_aligned());
+        let balance = |value: f64| {
+            let text = if value == 0.0 {
+                "0.00".to_string()
+            } else {
+                format!("{:+.2}", value)
+            };
+            Cell::from(Line::from(text).right_aligned()).style(self.theme.balance(value))
+        };
+        // Too narrow for all the columns, only the balance is kept.
+        let wide = area.width >= 36;
+        let row = |mut cells: Vec<Cell<'static>>| {
+            if !wide {
+                cells.drain(1..3);
+            }
+            Row::new(cells)
+        };
+        let (mut paid, mut consumed, mut total) = (0.0, 0.0, 0.0);
+        let mut rows: Vec<Row> = self
+            .users
+            .members()
+            .zip(&self.member_stats)
+            .map(|(m, stats)| {
+                // Paid and used count open expenses only, as in the member
+                // details, repayments show in the balance alone.
+                let (member_paid, member_consumed) = (stats.paid, stats.consumed);
+                paid += member_paid;
+                consumed += member_consumed;
+                total += m.balance();
+                row(vec![
+                    Cell::from(m.name().to_string()),
+                    number(member_paid),
+                    number(member_consumed),
+                    balance(m.balance()),
+                ])
+            })
+            .collect();
+        // The balances always add up to zero, anything else is a bug.
+        let (total, total_style) = if total.abs() < 0.005 {
+            ("0.00".to_string(), bold)
+        } else {
+            (
+                format!("{:+.2}", total),
+                self.theme.danger.add_modifier(Modifier::BOLD),
+            )
+        };
+        rows.push(
+            row(vec![
+                Cell::from("Total"),
+                number(paid),
+                number(consumed),
+                Cell::from(Line::from(total).right_aligned()),
+            ])
+            .style(total_style)
+            .top_margin(1),
+        );
+        let widths = if wide {
+            vec![
+                Constraint::Fill(1),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(9),
+            ]
+        } else {
+            vec![Constraint::Fill(1), Constraint::Length(9)]
+        };
+        let table = Table::new(rows, widths).header(
+            row(vec![
+                Cell::from("Name"),
+                Cell::from(Line::from("Paid").right_aligned()),
+                Cell::from(Line::from("Used").right_aligned()),
+                Cell::from(Line::from("Balance").right_aligned()),
+            ])
+            .style(bold),
+        );
+        frame.render_widget(table, table_area);
+        frame.render_widget(
+            Paragraph::new(footer).wrap(Wrap { trim: true }),
+            footer_area,
+        );
+    }
+
+    /// The Transactions table, with the filter being typed or the hint
+    /// under it.
+    fn draw_transactions(&mut self, frame: &mut Frame, area: Rect, hint: String, italic: bool) {
+        let removing = self.input_mode == InputMode::RemovingTransaction;
+        let mut footer: Vec<Line> = Vec::new();
+        if self.input_mode == InputMode::FilteringTransactions {
+            footer.extend(self.filter_input.lines("/", self.theme.danger));
+        } else if removing {
+            footer.push(Line::from("< select transaction to remove >"));
+        } else {
+            let style = if italic {
+                Style::default().add_modifier(Modifier::ITALIC)
+            } else {
+                Style::default()
+            };
+            footer.push(Line::styled(hint, style));
+        }
+        if self.transaction_history.is_empty() {
+            frame.render_widget(
+                Paragraph::new(footer)
+                    .alignment(Alignment::Left)
+                    .wrap(Wrap { trim: true }),
+                area,
+            );
+            return;
+        }
+        footer.insert(0, Line::from("----------"));
+        let footer_height: u16 = footer
+            .iter()
+            .map(|l| (l.width() as u16).div_ceil(area.width.max(1)).max(1))
+            .sum();
+        let [table_area, footer_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(footer_height)]).areas(area);
+
+        let visible = self.visible_transactions();
+        let payer_width = visible
+            .iter()
+            .map(|(_, t)| t.payer().chars().count())
+            .chain([TransactionColumn::Payer.title().len()])
+            .max()
+            .unwrap_or(0) as u16;
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|(_, t)| {
+                Row::new(vec![
+                    Cell::from(t.date().unwrap_or("-").to_string()),
+                    Cell::from(t.payer().to_string()),
+                    Cell::from(Line::from(format!("{:.2}", t.amount())).right_aligned()),
+                    Cell::from(t.description().to_string()),
+                    Cell::from(participant_names(t)),
+                ])
+            })
+            .collect();
+        let count = rows.len();
+        let header = Row::new(TransactionColumn::ALL.map(|column| {
+            let mut title = column.title().to_string();
+            if self.transaction_sort == Some(column) {
+                title.push_str(if self.sort_descending { " ▼" } else { " ▲" });
+            }
+            Cell::from(title)
+        }))
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let highlight = if removing {
+            self.theme.danger
+        } else {
+            self.theme.focus
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(payer_width),
+                Constraint::Length(10),
+                Constraint::Fill(2),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(highlight.add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+        // The filter may have left fewer rows than the selection.
+        if self
+            .transaction_table
+            .selected()
+            .is_some_and(|i| i >= count)
+        {
+            self.transaction_table.select(count.checked_sub(1));
+        }
+        self.transaction_page = (table_area.height as usize).saturating_sub(1);
+        frame.render_stateful_widget(table, table_area, &mut self.transaction_table);
+        // Below the header row.
+        let offset = self.transaction_table.offset();
+        for (i, y) in (offset..count).zip(table_area.y + 1..table_area.bottom()) {
+            let row = Rect::new(table_area.x, y, table_area.width, 1);
+            self.targets.push((row, Target::Transaction(i)));
+        }
+        frame.render_widget(
+            Paragraph::new(footer)
+                .alignment(Alignment::Left)
+                .wrap(Wrap { trim: true }),
+            footer_area,
+        );
+    }
+
+    /// Everything the selected member paid and took part in since the last
+    /// settling up, with their balance after each.
+    fn draw_member(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(detail) = self
+            .users
+            .list_users()
+            .get(self.selected_user_idx)
+            .and_then(|name| self.users.member_detail(name))
+        else {
+            return;
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.focus)
+            .title(Span::styled(
+                format!(" {} ", detail.name),
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .padding(Padding {
+                left: 1,
+                right: 1,
+                top: 1,
+                bottom: 1,
+            });
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(2),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let totals = Line::from(vec![
+            Span::raw(format!(
+                "Paid {:.2}   Consumed {:.2}   Repaid {:.2}   Received {:.2}   Balance ",
+                detail.paid, detail.consumed, detail.repaid, detail.received
+            )),
+            Span::styled(
+                format!("{:+.2}", detail.balance),
+                self.theme
+                    .balance(detail.balance)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]);
+        frame.render_widget(Paragraph::new(totals), rows[0]);
+
+        let number = |value: String| Cell::from(Line::from(value).right_aligned());
+        let table = Table::new(
+            detail.entries.iter().map(|e| {
+                Row::new(vec![
+                    Cell::from(e.date.clone().unwrap_or("-".to_string())),
+                    Cell::from(e.label()),
+                    Cell::from(e.payer.clone()),
+                    number(format!("{:.2}", e.paid)),
+                    number(format!("{:.2}", e.share)),
+                    number(format!("{:+.2}", e.balance)).style(self.theme.balance(e.balance)),
+                ])
+            }),
+            [
+                Constraint::Length(10),
+                Constraint::Min(10),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Length(12),
+                Constraint::Length(12),
+            ],
+        )
+        .header(
+            Row::new(vec![
+                Cell::from("Date"),
+                Cell::from("Description"),
+                Cell::from("Payer"),
+                number("Paid".to_string()),
+                number("Share".to_string()),
+                number("Balance".to_string()),
+            ])
+            .style(bold)
+            .bottom_margin(1),
+        )
+        .row_highlight_style(self.theme.focus.add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+        self.member_page = (rows[1].height as usize).saturating_sub(2);
+        frame.render_stateful_widget(table, rows[1], &mut self.member_table);
+        // Below the header row and its margin.
+        let offset = self.member_table.offset();
+        for (i, y) in (offset..detail.entries.len()).zip(rows[1].y + 2..rows[1].bottom()) {
+            let row = Rect::new(rows[1].x, y, rows[1].width, 1);
+            self.targets.push((row, Target::MemberEntry(i)));
+        }
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "< press {} or Esc to go back >",
+                self.keymap.label(Action::Member)
+            ))
+            .alignment(Alignment::Center),
+            rows[2],
+        );
+    }
+
+    /// Spending per member, category and month, settled expenses included.
+    fn draw_stats(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let report = self.users.report();
+        // Short terminals go without the gaps, narrow ones with narrower
+        // number columns.
+        let gap = if area.height < 20 { 0 } else { 1 };
+        let number_width = if area.width < 60 { 9 } else { 12 };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.theme.focus)
+            .title(Span::styled(
+                format!(" Stats: {:.2} spent ", report.total),
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .padding(Padding {
+                left: 1,
+                right: 1,
+                top: gap,
+                bottom: gap,
+            });
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(report.members.len() as u16 + 1 + gap),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+        // Under each other when there is no room beside, and the months
+        // left out when there is no room for them either.
+        let beside = rows[1].width >= 70;
+        let breakdowns = if beside || rows[1].height >= 8 {
+            Layout::default()
+                .direction(if beside {
+                    Direction::Horizontal
+                } else {
+                    Direction::Vertical
+                })
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .spacing(if beside { 4 } else { 1 })
+                .split(rows[1])
+                .to_vec()
+        } else {
+            vec![rows[1]]
+        };
+
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let number = |value: String| Cell::from(Line::from(value).right_aligned());
+        let members = Table::new(
+            report.members.iter().map(|m| {
+                Row::new(vec![
+                    Cell::from(m.name.clone()),
+                    number(format!("{:.2}", m.paid)),
+                    number(format!("{:.2}", m.consumed)),
+                    number(format!("{:+.2}", m.net)).style(self.theme.balance(m.net)),
+                ])
+            }),
+            [
+                Constraint::Min(6),
+                Constraint::Length(number_width),
+                Constraint::Length(number_width),
+                Constraint::Length(number_width),
+            ],
+        )
+        .header(
+            Row::new(vec![
+                Cell::from("Member"),
+                number("Paid".to_string()),
+                number("Consumed".to_string()),
+                number("Net".to_string()),
+            ])
+            .style(bold)
+            .bottom_margin(gap),
+        );
+        frame.render_widget(members, rows[0]);
+
+        for ((title, breakdown), area) in
+            [("Category", &report.categories), ("Month", &report.months)]
+                .into_iter()
+                .zip(breakdowns)
+        {
+            let table = Table::new(
+                breakdown.iter().map(|b| {
+                    Row::new(vec![
+                        Cell::from(b.name.clone()),
+                        number(format!("{:.2}", b.spent)),
+                        number(b.expenses.to_string()),
+                    ])
+                }),
+                [
+                    Constraint::Min(6),
+                    Constraint::Length(number_width),
+                    Constraint::Length(number_width),
+                ],
+            )
+            .header(
+                Row::new(vec![
+                    Cell::from(title),
+                    number("Spent".to_string()),
+                    number("Expenses".to_string()),
+                ])
+                .style(bold)
+                .bottom_margin(gap),
+            )
+            .block(Block::default().padding(Padding::top(gap)));
+            frame.render_widget(table, area);
+        }
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "< press {} or Esc to go back >",
+                self.keymap.label(Action::Stats)
+            ))
+            .alignment(Alignment::Center),
+            rows[2],
+        );
+    }
+
+    /// Spending per day, category and payer, and the balances over time.
+    /// Two charts a row when they fit, and on small terminals only the
+    /// first ones that do.
+    fn draw_charts(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let report = self.users.report();
+        let history = self.users.balance_history();
+        let [charts_area, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        let columns = if charts_area.width >= 2 * CHART_WIDTH {
+            2
+        } else {
+            1
+        };
+        let rows = (charts_area.height / CHART_HEIGHT).clamp(1, 4 / columns);
+        let charts: Vec<Rect> = Layout::vertical(vec![Constraint::Fill(1); rows as usize])
+            .split(charts_area)
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                // The wider half alternates, the balances getting it below.
+                let split = if i % 2 == 0 { [60, 40] } else { [40, 60] };
+                if columns == 2 {
+                    Layout::horizontal(split.map(Constraint::Percentage))
+                        .split(*row)
+                        .to_vec()
+                } else {
+                    vec![*row]
+                }
+            })
+            .collect();
+        let chart = |i: usize| charts.get(i).copied().unwrap_or_default();
+        let panel = |title: &str| {
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(
+                    format!(" {} ", title),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ))
+                .padding(Padding::horizontal(1))
+        };
+        let bar = |label: &str, amount: f64, color: Color| {
+            Bar::default()
+                .label(Line::from(label.to_string()))
+                .value(amount.round().max(0.0) as u64)
+                .text_value(format!("{:.0}", amount))
+                .style(Style::default().fg(color))
+        };
+
+        // Only the latest days that fit, "MM-DD" takes 5 columns.
+        let fit = (chart(0).width.saturating_sub(4) / 6) as usize;
+        let days = &report.days[report.days.len().saturating_sub(fit)..];
+        let per_day: Vec<Bar> = days
+            .iter()
+            .map(|d| {
+                bar(
+                    d.name.get(5..).unwrap_or(&d.name),
+                    d.spent,
+                    self.theme.chart[0],
+                )
+            })
+            .collect();
+        frame.render_widget(
+            BarChart::vertical(per_day)
+                .block(panel("Spent per day"))
+                .bar_width(5)
+                .bar_gap(1),
+            chart(0),
+        );
+
+        let per_category: Vec<Bar> = report
+            .categories
+            .iter()
+            .map(|c| bar(&c.name, c.spent, self.theme.chart[1]))
+            .collect();
+        frame.render_widget(
+            BarChart::horizontal(per_category)
+                .block(panel("Spent per category"))
+                .bar_gap(0),
+            chart(1),
+        );
+
+        let per_payer: Vec<Bar> = report
+            .members
+            .iter()
+            .map(|m| bar(&m.name, m.paid, self.theme.chart[2]))
+            .collect();
+        frame.render_widget(
+            BarChart::horizontal(per_payer)
+                .block(panel("Paid per member"))
+                .bar_gap(0),
+            chart(2),
+        );
+
+        let points = history.members.iter().flat_map(|m| m.1.iter());
+        let (x_min, x_max) = points.clone().fold((f64::MAX, f64::MIN), |(lo, hi), p| {
+            (lo.min(p.0), hi.max(p.0))
+        });
+        // Symmetric around 0, so that the middle label is right.
+        let y = points.fold(1.0f64, |y, p| y.max(p.1.abs()));
+        let (x_min, x_max) = if x_min > x_max {
+            (0.0, 1.0)
+        } else {
+            (x_min, x_max.max(x_min + 1.0))
+        };
+        let x_label = |x: f64| {
+            if history.dated {
+                crate::date_from_days(x as i64)
+            } else {
+                format!("#{}", x as i64)
+            }
+        };
+        let datasets = history
+            .members
+            .iter()
+            .enumerate()
+            .map(|(i, (name, points))| {
+                Dataset::default()
+                    .name(name.clone())
+                    .marker(ratatui::symbols::Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.theme.chart[i % self.theme.chart.len()]))
+                    .data(points)
+            })
+            .collect();
+        let balances = Chart::new(datasets)
+            .block(panel("Balances over time"))
+            .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+            .x_axis(
+                Axis::default()
+                    .bounds([x_min, x_max])
+                    .labels([x_label(x_min), x_label(x_max)]),
+            )
+            .y_axis(Axis::default().bounds([-y, y]).labels([
+                format!("{:.0}", -y),
+                "0".to_string(),
+                format!("{:.0}", y),
+            ]));
+        frame.render_widget(balances, chart(3));
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "< press {} or Esc to go back >",
+                self.keymap.label(Action::Charts)
+            ))
+            .alignment(Alignment::Center),
+            footer,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Arrangement;
+
+    #[test]
+    fn tabs_when_narrow_and_stacked_when_short() {
+        assert_eq!(Arrangement::for_size(80, 24), Arrangement::Stacked);
+        assert_eq!(Arrangement::for_size(150, 15), Arrangement::Stacked);
+        assert_eq!(Arrangement::for_size(60, 40), Arrangement::Tabs);
+        assert_eq!(Arrangement::for_size(150, 40), Arrangement::Columns);
     }
 }
@@ -0,0 +1,79 @@
+mod common;
+
+use common::members;
+use dangi_dongi::{Participant, Transaction, TransactionBuilder, format};
+
+#[test]
+fn records_refunds_but_not_zero() {
+    let mut users = members(&["Ali", "Sara"]);
+    for amount in [0.0, f64::NAN, f64::INFINITY] {
+        assert!(TransactionBuilder::new(&users, "Ali", amount).is_err());
+    }
+
+    let refund = TransactionBuilder::new(&users, "Ali", -20.0)
+        .unwrap()
+        .with_amount("Ali", -5.0)
+        .unwrap()
+        .with_amount("Sara", -15.0)
+        .unwrap()
+        .build()
+        .unwrap();
+    users.add_transaction(refund).unwrap();
+    assert_eq!(
+        users.balances(),
+        [("Ali".to_string(), -15.0), ("Sara".to_string(), 15.0)]
+    );
+
+    // Shares of a refund are refunds too.
+    let builder = TransactionBuilder::new(&users, "Ali", -20.0).unwrap();
+    assert!(builder.with_amount("Sara", 5.0).is_err());
+}
+
+#[test]
+fn editing_follows_the_same_rule() {
+    let mut users = members(&["Ali", "Sara"]);
+    let taxi = TransactionBuilder::new(&users, "Ali", 30.0)
+        .unwrap()
+        .build()
+        .unwrap();
+    users.add_transaction(taxi).unwrap();
+
+    let participants = vec![Participant::new("Ali", 1), Participant::new("Sara", 1)];
+    let error = users
+        .edit_transaction(0, Transaction::new(0.0, "Ali".to_string(), participants))
+        .unwrap_err();
+    assert!(error.contains("Invalid amount: 0"), "{}", error);
+    assert_eq!(users.transactions().next().unwrap().amount(), 30.0);
+}
+
+#[test]
+fn loading_follows_the_same_rule() {
+    let mut users = members(&["Ali", "Sara"]);
+    let taxi = TransactionBuilder::new(&users, "Ali", 30.0)
+        .unwrap()
+        .build()
+        .unwrap();
+    users.add_transaction(taxi).unwrap();
+    let json = format::to_json(&users).unwrap();
+
+    // The transaction as stored and as its event recorded it.
+    let mut zero: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let recorded = zero["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .position(|e| e["event"] == "expense_recorded")
+        .unwrap();
+    for transaction in [
+        "/transactions/0".to_string(),
+        format!("/events/{}/transaction", recorded),
+    ] {
+        let transaction = zero.pointer_mut(&transaction).unwrap();
+        transaction["amount"] = 0.0.into();
+        for participant in transaction["participants"].as_array_mut().unwrap() {
+            participant["fair_share"] = 0.0.into();
+        }
+    }
+    let error = format::from_json(&zero.to_string()).err().unwrap();
+    assert!(error.contains("Invalid amount: 0"), "{}", error);
+}
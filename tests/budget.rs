@@ -0,0 +1,38 @@
+mod common;
+
+use common::members;
+use dangi_dongi::budget::BudgetScope;
+use dangi_dongi::merge::merge;
+use dangi_dongi::{Users, format};
+
+fn group() -> Users {
+    let mut users = members(&["Ali", "Sara"]);
+    users.set_budget(BudgetScope::Group, 500.0).unwrap();
+    users
+}
+
+#[test]
+fn refuses_a_file_with_a_limit_of_zero() {
+    let json = format::to_json(&group()).unwrap();
+    assert!(format::from_json(&json).is_ok());
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let mut zero = value.clone();
+    zero["budgets"]["budgets"][0]["limit"] = 0.0.into();
+    assert!(format::from_json(&zero.to_string()).is_err());
+
+    let mut alert = value;
+    alert["budgets"]["alert_at"] = (-1.0).into();
+    assert!(format::from_json(&alert.to_string()).is_err());
+}
+
+#[test]
+fn merge_takes_their_alert_threshold() {
+    let base = group();
+    let ours = base.clone();
+    let mut theirs = base.clone();
+    theirs.set_budget_alert(90.0).unwrap();
+
+    let (merged, _) = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(merged.budget_alert(), 90.0);
+}
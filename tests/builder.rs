@@ -0,0 +1,22 @@
+mod common;
+
+use common::members;
+use dangi_dongi::TransactionBuilder;
+
+#[test]
+fn stores_dates_padded() {
+    let users = members(&["Ali", "Sara"]);
+    let transaction = TransactionBuilder::new(&users, "Ali", 30.0)
+        .unwrap()
+        .with_date("2026-1-5")
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(transaction.date(), Some("2026-01-05"));
+    assert!(
+        TransactionBuilder::new(&users, "Ali", 30.0)
+            .unwrap()
+            .with_date("2026-13-01")
+            .is_err()
+    );
+}
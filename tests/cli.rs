@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+
+use dangi_dongi::{cli, format};
+
+fn temp_file(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("dangi-dongi-cli-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Runs `args` against the group in `file`.
+fn run(file: &Path, args: &[&str]) -> Result<(), String> {
+    let mut items = vec!["--file".to_string(), file.display().to_string()];
+    items.extend(args.iter().map(|a| a.to_string()));
+    cli::run(items)
+}
+
+#[test]
+fn options_can_come_before_positionals() {
+    let file = temp_file("options.json");
+    run(&file, &["add-user", "Ali"]).unwrap();
+    run(&file, &["add-user", "Sara"]).unwrap();
+    run(
+        &file,
+        &[
+            "import-profile",
+            "add",
+            "--date",
+            "Date",
+            "--description",
+            "Text",
+            "--amount",
+            "Amount",
+            "bank",
+        ],
+    )
+    .unwrap();
+
+    let csv = temp_file("bank.csv");
+    std::fs::write(&csv, "Date,Text,Amount\n2026-01-05,Taxi,30\n").unwrap();
+    let csv = csv.display().to_string();
+    run(
+        &file,
+        &["import", "--profile", "bank", "--payer", "Ali", &csv],
+    )
+    .unwrap();
+
+    let users = format::load(&file.display().to_string()).unwrap();
+    assert_eq!(users.transactions().count(), 1);
+}
+
+#[test]
+fn adding_a_user_twice_fails() {
+    let file = temp_file("twice.json");
+    run(&file, &["add-user", "Ali"]).unwrap();
+    let error = run(&file, &["add-user", "Ali"]).unwrap_err();
+    assert!(error.contains("already exists"), "{}", error);
+}
+
+#[test]
+fn export_keeps_an_existing_file_without_force() {
+    let file = temp_file("export.json");
+    run(&file, &["add-user", "Ali"]).unwrap();
+    let output = temp_file("export.csv");
+    std::fs::write(&output, "mine").unwrap();
+    let output = output.display().to_string();
+
+    let error = run(&file, &["export", "csv", "--output", &output]).unwrap_err();
+    assert!(error.contains("--force"), "{}", error);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "mine");
+
+    run(&file, &["export", "--force", "csv", "--output", &output]).unwrap();
+    assert_ne!(std::fs::read_to_string(&output).unwrap(), "mine");
+}
+
+#[test]
+fn report_keeps_an_existing_file_without_force() {
+    let file = temp_file("report.json");
+    run(&file, &["add-user", "Ali"]).unwrap();
+    let output = temp_file("report.txt");
+    std::fs::write(&output, "mine").unwrap();
+    let output = output.display().to_string();
+
+    assert!(run(&file, &["report", "--output", &output]).is_err());
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "mine");
+    run(&file, &["report", "--output", &output, "--force"]).unwrap();
+    assert_ne!(std::fs::read_to_string(&output).unwrap(), "mine");
+}
@@ -0,0 +1,60 @@
+//! Groups the integration tests build on. Every test file uses only some
+//! of these.
+#![allow(dead_code)]
+
+use dangi_dongi::{TransactionBuilder, Users};
+
+/// A group of `names` without transactions.
+pub fn members(names: &[&str]) -> Users {
+    let mut users = Users::new();
+    for name in names {
+        users.add_member(name).unwrap();
+    }
+    users
+}
+
+/// Ali and Sara, Ali having paid 30 for a taxi.
+pub fn group() -> Users {
+    let mut users = members(&["Ali", "Sara"]);
+    add(&mut users, "Ali", 30.0, "taxi");
+    users
+}
+
+/// Records an expense split equally among everyone.
+pub fn add(users: &mut Users, payer: &str, amount: f64, description: &str) {
+    let transaction = TransactionBuilder::new(users, payer, amount)
+        .unwrap()
+        .with_description(description)
+        .build()
+        .unwrap();
+    users.add_transaction(transaction).unwrap();
+}
+
+/// Records an expense of `date` in `category`, split equally among
+/// everyone.
+pub fn add_dated(
+    users: &mut Users,
+    payer: &str,
+    amount: f64,
+    description: &str,
+    category: &str,
+    date: &str,
+) {
+    let transaction = TransactionBuilder::new(users, payer, amount)
+        .unwrap()
+        .with_description(description)
+        .with_category(category)
+        .with_date(date)
+        .unwrap()
+        .build()
+        .unwrap();
+    users.add_transaction(transaction).unwrap();
+}
+
+/// The descriptions of the open transactions, in order.
+pub fn descriptions(users: &Users) -> Vec<String> {
+    users
+        .transactions()
+        .map(|t| t.description().to_string())
+        .collect()
+}
@@ -0,0 +1,44 @@
+use dangi_dongi::crypto::{Key, is_encrypted};
+
+const GROUP: &str = r#"{"users": [], "transactions": []}"#;
+
+#[test]
+fn decrypts_what_it_encrypted() {
+    let data = Key::new("correct horse").unwrap().encrypt(GROUP).unwrap();
+    assert!(is_encrypted(&data));
+    assert!(!is_encrypted(GROUP));
+    assert!(!data.contains("transactions"));
+
+    let key = Key::for_file("correct horse", &data).unwrap();
+    assert_eq!(key.decrypt(&data).unwrap(), GROUP);
+}
+
+#[test]
+fn refuses_a_wrong_passphrase() {
+    let data = Key::new("correct horse").unwrap().encrypt(GROUP).unwrap();
+    let key = Key::for_file("battery staple", &data).unwrap();
+    assert!(key.decrypt(&data).is_err());
+    assert!(Key::new("").is_err());
+}
+
+#[test]
+fn refuses_tampered_data() {
+    let key = Key::new("correct horse").unwrap();
+    let data = key.encrypt(GROUP).unwrap();
+    let mut envelope: serde_json::Value = serde_json::from_str(&data).unwrap();
+
+    // A changed byte of the ciphertext.
+    let mut tampered = envelope.clone();
+    let ciphertext = tampered["ciphertext"].as_str().unwrap();
+    let flipped = if ciphertext.starts_with('A') {
+        "B"
+    } else {
+        "A"
+    };
+    tampered["ciphertext"] = format!("{}{}", flipped, &ciphertext[1..]).into();
+    assert!(key.decrypt(&tampered.to_string()).is_err());
+
+    // Other key derivation parameters.
+    envelope["kdf"]["iterations"] = 1.into();
+    assert!(key.decrypt(&envelope.to_string()).is_err());
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{add, descriptions, group};
+use dangi_dongi::events::SNAPSHOT_INTERVAL;
+use dangi_dongi::{Users, format};
+
+/// A group whose log runs past the first snapshot, with an edit, a
+/// removal and a settle-up on the way.
+fn long_group() -> Users {
+    let mut users = group();
+    for i in 0..SNAPSHOT_INTERVAL {
+        add(&mut users, "Sara", 1.0 + i as f64, &format!("coffee {}", i));
+        if i == 10 {
+            users.settle_up();
+        }
+    }
+    let edited = users.transactions().nth(5).unwrap().clone();
+    users
+        .edit_transaction(5, edited.with_description("tea"))
+        .unwrap();
+    users.remove_transaction(0).unwrap();
+    users
+}
+
+#[test]
+fn loading_replays_from_the_snapshot() {
+    let users = long_group();
+    let json = format::to_json(&users).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["snapshot"]["events"], SNAPSHOT_INTERVAL);
+
+    let loaded = format::from_json(&json).unwrap();
+    assert_eq!(loaded.balances(), users.balances());
+    assert_eq!(descriptions(&loaded), descriptions(&users));
+    assert!(descriptions(&loaded).contains(&"tea".to_string()));
+    assert!(loaded.verify().is_ok());
+}
+
+#[test]
+fn verify_finds_a_state_the_log_does_not_give() {
+    let json = format::to_json(&long_group()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    let mut stored = value.clone();
+    stored["users"][0]["net_balance"] = 1000.0.into();
+    let errors = format::read(&stored.to_string())
+        .unwrap()
+        .verify()
+        .unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].starts_with("stored state: Ali"), "{:?}", errors);
+
+    // Loading starts from the snapshot, so a wrong one carries over, but
+    // replaying the whole log shows it.
+    let mut snapshot = value;
+    snapshot["snapshot"]["users"][1]["net_balance"] = 1000.0.into();
+    let errors = format::read(&snapshot.to_string())
+        .unwrap()
+        .verify()
+        .unwrap_err();
+    assert!(errors[0].starts_with("snapshot: Sara"), "{:?}", errors);
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use common::{add, add_dated, members};
+use dangi_dongi::Users;
+
+/// Ali and Sara with an expense each, the descriptions needing escapes.
+fn group() -> Users {
+    let mut users = members(&["Ali", "Sara"]);
+    add_dated(
+        &mut users,
+        "Ali",
+        30.0,
+        "Taxi, \"night\"",
+        "travel",
+        "2026-01-05",
+    );
+    add_dated(
+        &mut users,
+        "Sara",
+        12.0,
+        "Fish & chips | drinks",
+        "food",
+        "2026-01-06",
+    );
+    users
+}
+
+/// The accounts beancount is told to open.
+fn opened(beancount: &str) -> Vec<&str> {
+    beancount
+        .lines()
+        .filter_map(|line| line.split(' ').nth(2).filter(|_| line.contains(" open ")))
+        .collect()
+}
+
+/// The amounts posted by each entry of a journal.
+fn postings(journal: &str) -> Vec<Vec<f64>> {
+    journal
+        .split("\n\n")
+        .filter(|entry| !entry.trim().is_empty() && !entry.contains(" open "))
+        .map(|entry| {
+            entry
+                .lines()
+                .skip(1)
+                .filter(|line| !line.contains(';') && !line.contains("category:"))
+                .map(|line| {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    fields[fields.len() - 2].parse().unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn ledger_and_beancount_entries_balance() {
+    let mut users = members(&["Ali", "Sara", "Reza"]);
+    add_dated(&mut users, "Ali", 10.0, "Coffee", "food", "2026-01-05");
+    add(&mut users, "Sara", 30.0, "Taxi");
+    users.set_currency("EUR").unwrap();
+
+    let ledger = users.to_ledger();
+    assert!(
+        ledger.starts_with("2026/01/05 * Coffee\n    ; category: food\n"),
+        "{}",
+        ledger
+    );
+    assert!(ledger.contains("! Reza pays Sara"), "{}", ledger);
+    let beancount = users.to_beancount();
+    assert!(
+        beancount.starts_with("2026-01-05 open Assets:Receivable:Ali EUR\n"),
+        "{}",
+        beancount
+    );
+    assert!(
+        beancount.contains("2026-01-05 * \"Coffee\"\n  category: \"food\"\n"),
+        "{}",
+        beancount
+    );
+
+    for journal in [ledger, beancount] {
+        let entries = postings(&journal);
+        // Two expenses and two transfers to settle them.
+        assert_eq!(entries.len(), 4, "{}", journal);
+        // A third of the coffee does not come out in cents, still every
+        // entry adds up to zero.
+        assert_eq!(entries[0], [10.0, -3.34, -3.33, -3.33]);
+        for entry in entries {
+            assert!(entry.iter().sum::<f64>().abs() < 1e-9, "{}", journal);
+        }
+        assert!(journal.contains("EUR"));
+    }
+}
+
+#[test]
+fn default_accounts_do_not_collide() {
+    let mut users = members(&["ali", "Ali", "رضا", "سارا", "Ömer"]);
+    add(&mut users, "ali", 40.0, "taxi");
+    users.set_account("Ömer", "Assets:Receivable:Ali").unwrap();
+
+    let beancount = users.to_beancount();
+    assert_eq!(
+        opened(&beancount),
+        [
+            "Assets:Receivable:Ali2",
+            "Assets:Receivable:Ali3",
+            "Assets:Receivable:M",
+            "Assets:Receivable:M2",
+            "Assets:Receivable:Ali",
+        ]
+    );
+}
+
+#[test]
+fn csv_has_a_row_per_share() {
+    let csv = group().to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(
+        lines[1],
+        r#"1,2026-01-05,"Taxi, ""night""",travel,expense,Ali,30.00,Ali,1,15.00"#
+    );
+    assert_eq!(
+        lines[4],
+        "2,2026-01-06,Fish & chips | drinks,food,expense,Sara,12.00,Sara,1,6.00"
+    );
+}
+
+#[test]
+fn markdown_and_html_show_totals_and_settlement() {
+    let users = group();
+
+    let markdown = users.to_markdown();
+    assert!(
+        markdown.contains("| Ali | 30.00 | 21.00 | +9.00 |"),
+        "{}",
+        markdown
+    );
+    assert!(markdown.contains("Fish & chips \\| drinks"), "{}", markdown);
+    assert!(markdown.contains("- Sara pays Ali 9.00"), "{}", markdown);
+
+    let html = users.to_html();
+    assert!(html.contains("Taxi, &quot;night&quot;"), "{}", html);
+    assert!(html.contains("Fish &amp; chips | drinks"), "{}", html);
+    assert!(
+        html.contains("<td class=\"number owed\">+9.00</td>"),
+        "{}",
+        html
+    );
+    assert!(html.contains("<li>Sara pays Ali 9.00</li>"), "{}", html);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{add_dated, members};
+use dangi_dongi::filter::TransactionFilter;
+use dangi_dongi::{TransactionBuilder, Users};
+
+fn group() -> Users {
+    let mut users = members(&["Ali", "Sara", "Reza"]);
+    add_dated(
+        &mut users,
+        "Ali",
+        30.0,
+        "Taxi to the airport",
+        "travel",
+        "2026-01-05",
+    );
+    add_dated(&mut users, "Sara", 12.0, "Coffee", "food", "2026-01-20");
+    add_dated(&mut users, "Reza", 45.0, "Dinner", "food", "2026-02-02");
+    // Sara is not in on the last one.
+    let transaction = TransactionBuilder::new(&users, "Ali", 8.0)
+        .unwrap()
+        .with_description("Taxi home")
+        .with_weight("Ali", 1)
+        .unwrap()
+        .with_weight("Reza", 1)
+        .unwrap()
+        .build()
+        .unwrap();
+    users.add_transaction(transaction).unwrap();
+    users
+}
+
+fn shown(query: &str) -> Vec<String> {
+    let filter = TransactionFilter::parse(query).unwrap();
+    group()
+        .transactions()
+        .filter(|t| filter.matches(t))
+        .map(|t| t.description().to_string())
+        .collect()
+}
+
+#[test]
+fn every_part_has_to_match() {
+    assert_eq!(shown("").len(), 4);
+    assert_eq!(shown("TAXI"), ["Taxi to the airport", "Taxi home"]);
+    assert_eq!(
+        shown("taxi payer:ali"),
+        ["Taxi to the airport", "Taxi home"]
+    );
+    assert_eq!(shown("taxi with:sara"), ["Taxi to the airport"]);
+    assert_eq!(shown("category:Food payer:Reza"), ["Dinner"]);
+}
+
+#[test]
+fn dates_are_inclusive_and_undated_ones_fall_outside() {
+    assert_eq!(
+        shown("from:2026-01-05 to:2026-01-20"),
+        ["Taxi to the airport", "Coffee"]
+    );
+    assert_eq!(shown("from:2026-02-01"), ["Dinner"]);
+    // Compared as text, 2026-1-20 would come after every January day.
+    assert_eq!(
+        shown("from:2026-1-5 to:2026-1-20"),
+        ["Taxi to the airport", "Coffee"]
+    );
+}
+
+#[test]
+fn refuses_what_it_does_not_understand() {
+    for query in ["payer:", "amount:5", "from:2026-13-01", "to:yesterday"] {
+        assert!(TransactionFilter::parse(query).is_err(), "{}", query);
+    }
+    assert_eq!(
+        TransactionFilter::parse("  taxi   with:Ali ").unwrap(),
+        TransactionFilter::parse("taxi with:ali").unwrap()
+    );
+}
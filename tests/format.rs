@@ -0,0 +1,80 @@
+use dangi_dongi::format;
+
+/// A group file from before the format was versioned.
+const V0: &str = r#"{
+  "users": [
+    {"name": "Ali", "amount_paid": 30.0, "net_balance": 15.0},
+    {"name": "Sara", "amount_paid": 0.0, "net_balance": -15.0}
+  ],
+  "transactions": [
+    {
+      "amount": 30.0,
+      "payer": "Ali",
+      "participants": [
+        {"name": "Ali", "weight": 1, "fair_share": 15.0},
+        {"name": "Sara", "weight": 1, "fair_share": 15.0}
+      ]
+    }
+  ]
+}"#;
+
+#[test]
+fn migrates_an_unversioned_file() {
+    let users = format::from_json(V0).unwrap();
+    assert_eq!(
+        users.balances(),
+        [("Ali".to_string(), 15.0), ("Sara".to_string(), -15.0)]
+    );
+    // The log is the members joining and the expense being recorded.
+    assert_eq!(users.events().len(), 3);
+    let transaction = users.transactions().next().unwrap();
+    assert!(!transaction.id().is_empty());
+
+    let json = format::to_json(&users).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"], format::CURRENT_VERSION);
+    let again = format::from_json(&json).unwrap();
+    assert_eq!(again.transactions().next().unwrap().id(), transaction.id());
+}
+
+#[test]
+fn refuses_files_it_cannot_read() {
+    let newer = V0.replacen('{', r#"{"version": 99,"#, 1);
+    let error = format::from_json(&newer).unwrap_err();
+    assert!(error.contains("newer version"), "{}", error);
+
+    let error = format::from_json(r#"{"users": []}"#).unwrap_err();
+    assert!(error.contains("migrating from format 0"), "{}", error);
+}
+
+#[test]
+fn lists_every_problem_of_a_file() {
+    let broken = V0
+        .replace("\"fair_share\": 15.0}\n", "\"fair_share\": 10.0}\n")
+        .replacen(
+            '{',
+            r#"{"presets": [{"name": "lunch", "payer": "Reza", "participants": [], "split": "Equal", "category": null, "amount": 0.0}],"#,
+            1,
+        );
+    let error = format::from_json(&broken).unwrap_err();
+    assert!(
+        error.contains("shares add up to 25.00 instead of 30.00"),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains("preset lunch: Reza is not a member"),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains("preset lunch: Invalid amount: 0"),
+        "{}",
+        error
+    );
+
+    // The log is replayed first, and a stranger paying stops it.
+    let stranger = V0.replace(r#""payer": "Ali""#, r#""payer": "Reza""#);
+    let error = format::from_json(&stranger).unwrap_err();
+    assert!(error.contains("Reza is not a member"), "{}", error);
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use common::members;
+use dangi_dongi::import::{ImportProfile, from_splitwise, from_tricount};
+use dangi_dongi::{Participant, Preset, SplitType, Users};
+
+const STATEMENT: &str = "Date,Text,Amount
+2026-01-05,Taxi,30
+2026-01-06,Refund for the taxi,-10
+";
+
+/// Ali and Sara with a `bank` profile, Ali paying.
+fn group() -> Users {
+    let mut users = members(&["Ali", "Sara"]);
+    let profile =
+        ImportProfile::new("bank", "Date", "Text", "Amount").with_payer(Some("Ali".to_string()));
+    users.add_import_profile(profile).unwrap();
+    users
+}
+
+#[test]
+fn records_negative_rows_as_refunds() {
+    let mut users = group();
+    let report = users.import_csv("bank", STATEMENT, None).unwrap();
+    assert_eq!(report.imported, 2);
+    assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+    assert_eq!(
+        users.balances(),
+        [("Ali".to_string(), 10.0), ("Sara".to_string(), -10.0)]
+    );
+}
+
+#[test]
+fn settled_rows_are_still_duplicates() {
+    let mut users = group();
+    users.import_csv("bank", STATEMENT, None).unwrap();
+    users.settle_up();
+
+    let report = users.import_csv("bank", STATEMENT, None).unwrap();
+    assert_eq!((report.imported, report.duplicates), (0, 2));
+    assert_eq!(users.transactions().count(), 0);
+}
+
+#[test]
+fn reads_a_statement_through_its_profile() {
+    let mut users = members(&["Ali", "Sara"]);
+    let profile = ImportProfile::new("bank", "Datum", "Text", "Betrag")
+        .with_date_format("%d.%m.%Y")
+        .with_delimiter(';')
+        .with_negate(true)
+        .with_decimal_comma(true)
+        .with_payer(Some("Sara".to_string()));
+    users.add_import_profile(profile).unwrap();
+    let lunch = Preset::new("lunch", SplitType::Equal)
+        .with_participants(vec![Participant::new("Ali", 1)])
+        .with_category(Some("food".to_string()));
+    users.add_preset(lunch).unwrap();
+    users.add_import_rule("bank", "LIDL", "lunch").unwrap();
+
+    let statement = "Datum;Text;Betrag
+05.01.2026;\"Taxi; airport\";-1.230,50
+06.01.2026;Lidl Berlin;-12,00
+";
+    let report = users.import_csv("bank", statement, None).unwrap();
+    assert_eq!(report.imported, 2);
+
+    let transactions: Vec<_> = users.transactions().collect();
+    assert_eq!(transactions[0].description(), "Taxi; airport");
+    assert_eq!(transactions[0].amount(), 1230.5);
+    assert_eq!(transactions[0].date(), Some("2026-01-05"));
+    // The rule split the lunch the preset's way.
+    assert_eq!(transactions[1].category(), Some("food"));
+    assert_eq!(
+        users.balances(),
+        [("Ali".to_string(), -627.25), ("Sara".to_string(), 627.25)]
+    );
+}
+
+#[test]
+fn reports_the_rows_it_leaves_out() {
+    let mut users = group();
+    let statement = "Date,Text,Amount
+2026-01-05,Taxi,30
+2026-01-05,Taxi,30
+yesterday,Bread,3
+2026-01-07,Coffee,a lot
+";
+    let report = users.import_csv("bank", statement, None).unwrap();
+    // Equal rows of one statement are both expenses.
+    assert_eq!((report.imported, report.duplicates), (2, 0));
+    assert_eq!(report.skipped.len(), 2);
+    assert!(
+        report.skipped[0].starts_with("row 4:"),
+        "{:?}",
+        report.skipped
+    );
+    assert!(
+        report.skipped[1].starts_with("row 5:"),
+        "{:?}",
+        report.skipped
+    );
+
+    let report = users.import_csv("bank", statement, None).unwrap();
+    assert_eq!((report.imported, report.duplicates), (0, 2));
+    assert!(users.import_csv("card", statement, None).is_err());
+}
+
+const SPLITWISE: &str = "Date,Description,Category,Cost,Currency,Ali,Sara,Reza
+2026-01-05,Dinner,Dining out,90.00,EUR,60.00,-30.00,-30.00
+2026-01-06,Payment,Payment,30.00,EUR,-30.00,30.00,0.00
+2026-01-07,Taxi,Taxi,20.00,EUR,-10.00,10.00,0.00
+2026-01-07,Total balance, , ,EUR,20.00,10.00,-30.00
+";
+
+const TRICOUNT: &str = "Title,Amount,Paid by,Date,Transaction type,Impacted to Ali,Impacted to Sara
+Groceries,40,Ali,2026-01-05,Normal,20,20
+Back for the groceries,10,Sara,06/01/2026,Money transfer,10,0
+Salary,100,Ali,2026-01-07,Income,50,50
+Balance,,,,,10,-10
+";
+
+#[test]
+fn splitwise_balances_are_checked() {
+    let (users, report) = from_splitwise(SPLITWISE).unwrap();
+    assert_eq!(report.imported, 3);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    assert_eq!(
+        users.balances(),
+        [
+            ("Ali".to_string(), 20.0),
+            ("Sara".to_string(), 10.0),
+            ("Reza".to_string(), -30.0)
+        ]
+    );
+    // The payment is a repayment, not an expense.
+    assert_eq!(users.transactions().filter(|t| t.is_repayment()).count(), 1);
+
+    let (_, report) =
+        from_splitwise(&SPLITWISE.replace("10.00,-30.00\n", "10.00,-25.00\n")).unwrap();
+    assert_eq!(report.warnings.len(), 1);
+    assert!(
+        report.warnings[0].contains("Balance of Reza"),
+        "{:?}",
+        report.warnings
+    );
+}
+
+#[test]
+fn tricount_balances_are_checked() {
+    let (users, report) = from_tricount(TRICOUNT).unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.skipped.len(), 1, "{:?}", report.skipped);
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    assert_eq!(
+        users.balances(),
+        [("Ali".to_string(), 10.0), ("Sara".to_string(), -10.0)]
+    );
+    let dates: Vec<_> = users.transactions().map(|t| t.date()).collect();
+    assert_eq!(dates, [Some("2026-01-05"), Some("2026-01-06")]);
+
+    let (_, report) = from_tricount(&TRICOUNT.replace(",10,-10", ",15,-15")).unwrap();
+    assert_eq!(report.warnings.len(), 2, "{:?}", report.warnings);
+}
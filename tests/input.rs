@@ -0,0 +1,47 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use dangi_dongi::input::{TextInput, Validator};
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+#[test]
+fn edits_whole_graphemes() {
+    let mut input = TextInput::new(Validator::Text);
+    // An accent made of two code points, and a family emoji of several.
+    input.insert("cafe\u{301} 👨‍👩‍👧");
+    input.backspace();
+    assert_eq!(input.value(), "cafe\u{301} ");
+
+    input.handle_key(key(KeyCode::Left));
+    input.handle_key(key(KeyCode::Left));
+    input.delete();
+    assert_eq!(input.value(), "caf ");
+
+    input.handle_key(key(KeyCode::Home));
+    input.handle_key(key(KeyCode::Right));
+    input.insert("ß");
+    input.handle_key(key(KeyCode::End));
+    input.handle_key(key(KeyCode::Char('!')));
+    assert_eq!(input.value(), "cßaf !");
+}
+
+#[test]
+fn leaves_out_pasted_line_breaks() {
+    let mut input = TextInput::new(Validator::Text);
+    input.insert("two\r\nlines\t");
+    assert_eq!(input.value(), "twolines");
+    // Ctrl+ keys are for the TUI, not typed.
+    assert!(!input.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+    assert_eq!(input.value(), "twolines");
+}
+
+#[test]
+fn amounts_may_be_refunds_but_not_zero() {
+    let amount = Validator::Amount;
+    assert!(amount.check("12.5").is_ok());
+    assert!(amount.check("-12.5").is_ok());
+    for value in ["0", "-0", "inf", "NaN", "12,5", ""] {
+        assert!(amount.check(value).is_err(), "{}", value);
+    }
+}
@@ -0,0 +1,48 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use dangi_dongi::keymap::{Action, Keymap};
+
+fn press(c: char, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), modifiers)
+}
+
+#[test]
+fn refuses_a_key_bound_twice() {
+    let error = Keymap::from_json(r#"{"quit": "u"}"#).err().unwrap();
+    assert!(error.contains("'u' is bound to both"), "{}", error);
+    assert!(Keymap::from_json(r#"{"quit": "x", "help": ["x", "f1"]}"#).is_err());
+    // Moving the default key away frees it.
+    assert!(Keymap::from_json(r#"{"quit": "u", "add_user": "a"}"#).is_ok());
+}
+
+#[test]
+fn refuses_the_keys_of_the_inputs() {
+    for key in ["left", "Right", "up", "enter", "esc", "pagedown"] {
+        let json = format!(r#"{{"quit": "{}"}}"#, key);
+        assert!(Keymap::from_json(&json).is_err(), "{}", key);
+    }
+    assert!(Keymap::from_json(r#"{"quit": "f13"}"#).is_err());
+}
+
+#[test]
+fn modifiers_have_to_match() {
+    let keymap = Keymap::from_json(r#"{"quit": ["q", "ctrl+x"]}"#).unwrap();
+    assert_eq!(
+        keymap.action(&press('q', KeyModifiers::NONE)),
+        Some(Action::Quit)
+    );
+    assert_eq!(keymap.action(&press('q', KeyModifiers::ALT)), None);
+    assert_eq!(keymap.action(&press('q', KeyModifiers::CONTROL)), None);
+    assert_eq!(
+        keymap.action(&press('x', KeyModifiers::CONTROL)),
+        Some(Action::Quit)
+    );
+    assert_eq!(
+        keymap.action(&press('x', KeyModifiers::CONTROL | KeyModifiers::ALT)),
+        None
+    );
+    // Shift is how '?' is typed.
+    assert_eq!(
+        keymap.action(&press('?', KeyModifiers::SHIFT)),
+        Some(Action::Help)
+    );
+}
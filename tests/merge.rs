@@ -0,0 +1,73 @@
+mod common;
+
+use common::{add, descriptions};
+use dangi_dongi::merge::merge;
+use dangi_dongi::{TransactionBuilder, Users};
+
+/// The common group with a coffee of Sara's.
+fn group() -> Users {
+    let mut users = common::group();
+    add(&mut users, "Sara", 12.0, "coffee");
+    users
+}
+
+fn edit(users: &mut Users, index: usize, amount: f64, description: &str) {
+    let payer = users.transactions().nth(index).unwrap().payer().to_string();
+    let transaction = TransactionBuilder::new(users, &payer, amount)
+        .unwrap()
+        .with_description(description)
+        .build()
+        .unwrap();
+    users.edit_transaction(index, transaction).unwrap();
+}
+
+#[test]
+fn takes_the_changes_of_both_sides() {
+    let base = group();
+    let mut ours = base.clone();
+    let mut theirs = base.clone();
+    add(&mut ours, "Ali", 8.0, "bread");
+    edit(&mut theirs, 0, 32.0, "taxi and tip");
+    theirs.remove_transaction(1).unwrap();
+
+    let (merged, report) = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(descriptions(&merged), ["taxi and tip", "bread"]);
+    assert_eq!((report.added, report.edited, report.removed), (0, 1, 1));
+    assert!(merged.conflicts().is_empty());
+    // Our log is kept, theirs comes after it.
+    assert_eq!(merged.events()[..ours.events().len()], *ours.events());
+}
+
+#[test]
+fn keeps_ours_and_records_a_conflict() {
+    let base = group();
+    let mut ours = base.clone();
+    let mut theirs = base.clone();
+    edit(&mut ours, 0, 31.0, "taxi to the airport");
+    edit(&mut theirs, 0, 35.0, "taxi home");
+    theirs.remove_transaction(1).unwrap();
+    edit(&mut ours, 1, 13.0, "coffee and cake");
+
+    let (mut merged, report) = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(report.conflicts, 2);
+    assert_eq!(
+        descriptions(&merged),
+        ["taxi to the airport", "coffee and cake"]
+    );
+
+    merged.resolve_conflict(1, true).unwrap();
+    merged.resolve_conflict(0, true).unwrap();
+    assert!(merged.conflicts().is_empty());
+    assert_eq!(descriptions(&merged), ["taxi home"]);
+}
+
+#[test]
+fn merging_the_same_copy_changes_nothing() {
+    let base = group();
+    let mut ours = base.clone();
+    add(&mut ours, "Sara", 4.0, "water");
+
+    let (merged, report) = merge(&base, &ours, &ours).unwrap();
+    assert_eq!(merged.events(), ours.events());
+    assert_eq!(report.added + report.edited + report.removed, 0);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::{add, add_dated, members};
+use dangi_dongi::{TransactionBuilder, Users};
+
+/// Two expenses settled up, then an expense and a repayment still open.
+fn group() -> Users {
+    let mut users = members(&["Ali", "Sara"]);
+    add_dated(&mut users, "Ali", 30.0, "taxi", "travel", "2026-01-05");
+    add_dated(&mut users, "Sara", 12.0, "coffee", "food", "2026-01-20");
+    users.settle_up();
+    add_dated(&mut users, "Ali", 8.0, "bread", "food", "2026-02-02");
+    users
+        .record_repayment("Sara", "Ali", 2.0, Some("2026-02-03".to_string()))
+        .unwrap();
+    users
+}
+
+#[test]
+fn report_counts_settled_expenses_but_not_repayments() {
+    let mut users = group();
+    add(&mut users, "Sara", 4.0, "gum");
+    let report = users.report();
+
+    assert_eq!(report.total, 54.0);
+    let members: Vec<_> = report
+        .members
+        .iter()
+        .map(|m| (m.name.as_str(), m.paid, m.consumed, m.net))
+        .collect();
+    assert_eq!(
+        members,
+        [("Ali", 38.0, 27.0, 11.0), ("Sara", 16.0, 27.0, -11.0)]
+    );
+    let names = |breakdowns: &[dangi_dongi::reports::Breakdown]| {
+        breakdowns
+            .iter()
+            .map(|b| (b.name.clone(), b.spent))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(&report.categories),
+        [
+            ("travel".to_string(), 30.0),
+            ("food".to_string(), 20.0),
+            ("uncategorized".to_string(), 4.0)
+        ]
+    );
+    assert_eq!(
+        names(&report.months),
+        [
+            ("2026-01".to_string(), 42.0),
+            ("2026-02".to_string(), 8.0),
+            ("undated".to_string(), 4.0)
+        ]
+    );
+    assert_eq!(report.days.len(), 3);
+}
+
+#[test]
+fn balance_history_follows_the_log() {
+    let history = group().balance_history();
+    assert!(history.dated);
+    let (name, points) = &history.members[0];
+    assert_eq!(name, "Ali");
+    let balances: Vec<f64> = points.iter().map(|p| p.1).collect();
+    // Settling up brings Ali back to zero.
+    assert_eq!(balances, [15.0, 9.0, 0.0, 4.0, 2.0]);
+    let days: Vec<f64> = points.iter().map(|p| p.0 - points[0].0).collect();
+    assert_eq!(days, [0.0, 15.0, 15.0, 28.0, 29.0]);
+
+    // Without dates the changes are counted instead.
+    let mut undated = members(&["Ali", "Sara"]);
+    add(&mut undated, "Ali", 10.0, "taxi");
+    add(&mut undated, "Sara", 4.0, "gum");
+    let history = undated.balance_history();
+    assert!(!history.dated);
+    assert_eq!(history.members[1].1, [(1.0, -5.0), (2.0, -3.0)]);
+}
+
+#[test]
+fn member_detail_ends_at_the_balance() {
+    let mut users = group();
+    let transaction = TransactionBuilder::new(&users, "Sara", 9.0)
+        .unwrap()
+        .with_weight("Ali", 2)
+        .unwrap()
+        .with_weight("Sara", 1)
+        .unwrap()
+        .build()
+        .unwrap();
+    users.add_transaction(transaction).unwrap();
+
+    for (name, balance) in users.balances() {
+        let detail = users.member_detail(&name).unwrap();
+        assert!((detail.balance - balance).abs() < 1e-9, "{}", name);
+        let last = detail.entries.last().unwrap();
+        assert!((last.balance - balance).abs() < 1e-9, "{}", name);
+        let worked_out = detail.paid - detail.consumed + detail.repaid - detail.received;
+        assert!((worked_out - balance).abs() < 1e-9, "{}", name);
+    }
+
+    // Only the open transactions are in it.
+    let ali = users.member_detail("Ali").unwrap();
+    assert_eq!(ali.entries.len(), 3);
+    assert_eq!((ali.paid, ali.consumed, ali.received), (8.0, 10.0, 2.0));
+    assert!(users.member_detail("Reza").is_none());
+}
@@ -0,0 +1,180 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use dangi_dongi::Users;
+use dangi_dongi::server::Server;
+use dangi_dongi::storage::Storage;
+
+const TOKEN: &str = "secret";
+
+/// Keeps the last saved group in memory, or fails to save when told to.
+#[derive(Clone, Default)]
+struct Memory {
+    saved: Arc<Mutex<Option<Users>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl Storage for Memory {
+    fn exists(&self) -> bool {
+        self.saved.lock().unwrap().is_some()
+    }
+
+    fn load(&self) -> Result<Users, String> {
+        self.load_stored()
+    }
+
+    fn load_stored(&self) -> Result<Users, String> {
+        self.saved
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("Nothing saved".to_string())
+    }
+
+    fn save(&self, users: &Users) -> Result<(), String> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err("disk full".to_string());
+        }
+        *self.saved.lock().unwrap() = Some(users.clone());
+        Ok(())
+    }
+}
+
+fn start() -> (SocketAddr, Memory) {
+    let mut users = Users::new();
+    for name in ["Ali", "Sara"] {
+        users.add_member(name).unwrap();
+    }
+    let storage = Memory::default();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let memory = storage.clone();
+    std::thread::spawn(move || {
+        let server = Server::bind(Box::new(memory), users, "127.0.0.1:0", TOKEN).unwrap();
+        sender.send(server.address().unwrap()).unwrap();
+        server.run()
+    });
+    (receiver.recv().unwrap(), storage)
+}
+
+struct Answer {
+    status: u16,
+    etag: String,
+    body: serde_json::Value,
+}
+
+fn request(
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> Answer {
+    let mut stream = TcpStream::connect(address).unwrap();
+    let mut message = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        address,
+        body.len()
+    );
+    for (field, value) in headers {
+        message.push_str(&format!("{}: {}\r\n", field, value));
+    }
+    message.push_str("\r\n");
+    message.push_str(body);
+    stream.write_all(message.as_bytes()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    let etag = head
+        .lines()
+        .find_map(|l| l.strip_prefix("ETag: "))
+        .unwrap_or("")
+        .to_string();
+    Answer {
+        status,
+        etag,
+        body: serde_json::from_str(body).unwrap(),
+    }
+}
+
+fn authorized(
+    address: SocketAddr,
+    method: &str,
+    path: &str,
+    extra: &[(&str, &str)],
+    body: &str,
+) -> Answer {
+    let bearer = format!("Bearer {}", TOKEN);
+    let mut headers = vec![("Authorization", bearer.as_str())];
+    headers.extend_from_slice(extra);
+    request(address, method, path, &headers, body)
+}
+
+#[test]
+fn needs_the_token() {
+    let (address, _) = start();
+    assert_eq!(request(address, "GET", "/members", &[], "").status, 401);
+    let wrong = [("Authorization", "Bearer guess")];
+    assert_eq!(request(address, "GET", "/members", &wrong, "").status, 401);
+    let answer = authorized(address, "GET", "/members", &[], "");
+    assert_eq!(answer.status, 200);
+    assert_eq!(answer.body[1]["name"], "Sara");
+}
+
+#[test]
+fn refuses_a_write_on_an_old_version() {
+    let (address, storage) = start();
+    let version = authorized(address, "GET", "/members", &[], "").etag;
+    assert!(
+        version.starts_with('"') && version.ends_with('"'),
+        "{}",
+        version
+    );
+
+    let expense = r#"{"payer": "Ali", "amount": 30, "description": "taxi"}"#;
+    let answer = authorized(
+        address,
+        "POST",
+        "/transactions",
+        &[("If-Match", &version)],
+        expense,
+    );
+    assert_eq!(answer.status, 201);
+    assert_ne!(answer.etag, version);
+    assert!(storage.exists());
+
+    let answer = authorized(
+        address,
+        "POST",
+        "/transactions",
+        &[("If-Match", &version)],
+        expense,
+    );
+    assert_eq!(answer.status, 412);
+    let transactions = authorized(address, "GET", "/transactions", &[], "").body;
+    assert_eq!(transactions.as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn rolls_back_what_could_not_be_saved() {
+    let (address, storage) = start();
+    let version = authorized(address, "GET", "/members", &[], "").etag;
+
+    storage.failing.store(true, Ordering::SeqCst);
+    let answer = authorized(address, "POST", "/members", &[], r#"{"name": "Reza"}"#);
+    assert_eq!(answer.status, 500);
+    let answer = authorized(address, "GET", "/members", &[], "");
+    assert_eq!(answer.etag, version);
+    assert_eq!(answer.body.as_array().unwrap().len(), 2);
+
+    storage.failing.store(false, Ordering::SeqCst);
+    let answer = authorized(address, "POST", "/members", &[], r#"{"name": "Ali"}"#);
+    assert_eq!(answer.status, 400);
+    assert_eq!(answer.etag, version);
+    assert!(!storage.exists());
+}
@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+
+mod common;
+
+use common::{add, members};
+use dangi_dongi::storage::{Sqlite, Storage};
+use dangi_dongi::{TransactionBuilder, Users, format};
+
+fn temp_db(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("dangi-dongi-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn group() -> Users {
+    let mut users = members(&["Ali", "Sara", "Reza"]);
+    add(&mut users, "Ali", 30.0, "taxi");
+    add(&mut users, "Sara", 12.0, "coffee");
+    add(&mut users, "Reza", 9.0, "bread");
+    users
+}
+
+fn rows(path: &PathBuf) -> Vec<(i64, String)> {
+    let db = rusqlite::Connection::open(path).unwrap();
+    let mut query = db
+        .prepare("SELECT id, description FROM transactions ORDER BY id")
+        .unwrap();
+    query
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn saves_only_the_rows_that_changed() {
+    let path = temp_db("incremental");
+    let storage = Sqlite::new(path.to_str().unwrap());
+    let mut users = group();
+    storage.save(&users).unwrap();
+    let before = rows(&path);
+
+    users.remove_transaction(1).unwrap();
+    let transaction = TransactionBuilder::new(&users, "Ali", 5.0)
+        .unwrap()
+        .with_description("tea")
+        .build()
+        .unwrap();
+    users.add_transaction(transaction).unwrap();
+    storage.save(&users).unwrap();
+
+    let after = rows(&path);
+    assert_eq!(after[0], before[0]);
+    assert_eq!(after[1], before[2]);
+    assert_eq!(after[2].1, "tea");
+    assert!(after[2].0 > before[2].0);
+    assert_eq!(
+        format::to_json(&storage.load().unwrap()).unwrap(),
+        format::to_json(&users).unwrap()
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn edits_and_removed_members_round_trip() {
+    let path = temp_db("edits");
+    let storage = Sqlite::new(path.to_str().unwrap());
+    let mut users = group();
+    users.add_member("Zed").unwrap();
+    storage.save(&users).unwrap();
+
+    users.remove_member("Zed").unwrap();
+    let transaction = TransactionBuilder::new(&users, "Sara", 14.0)
+        .unwrap()
+        .with_description("coffee and cake")
+        .build()
+        .unwrap();
+    users.edit_transaction(1, transaction).unwrap();
+    storage.save(&users).unwrap();
+
+    let loaded = storage.load().unwrap();
+    assert_eq!(loaded.list_users(), ["Ali", "Sara", "Reza"]);
+    assert_eq!(
+        format::to_json(&loaded).unwrap(),
+        format::to_json(&users).unwrap()
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn never_rewrites_stored_events() {
+    let path = temp_db("append-only");
+    let storage = Sqlite::new(path.to_str().unwrap());
+    let users = group();
+    storage.save(&users).unwrap();
+
+    let mut other = group();
+    other.remove_transaction(0).unwrap();
+    assert!(storage.save(&other).is_err());
+    assert_eq!(storage.load().unwrap().events().len(), users.events().len());
+    let _ = std::fs::remove_file(&path);
+}
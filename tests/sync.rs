@@ -0,0 +1,83 @@
+use std::net::TcpListener;
+
+mod common;
+
+use common::{add, group};
+use dangi_dongi::Users;
+use dangi_dongi::crypto::Key;
+use dangi_dongi::sync::{SyncReport, answer, connect};
+
+/// The descriptions in any order, each side listing the other's changes
+/// last.
+fn descriptions(users: &Users) -> Vec<String> {
+    let mut descriptions = common::descriptions(users);
+    descriptions.sort();
+    descriptions
+}
+
+/// Syncs `ours` with a peer answering with `theirs` over 127.0.0.1.
+fn sync(
+    ours: &Users,
+    our_key: &Key,
+    theirs: &Users,
+    their_key: &Key,
+) -> (Result<SyncReport, String>, Result<SyncReport, String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    std::thread::scope(|scope| {
+        let peer = scope.spawn(|| {
+            let (stream, _) = listener.accept().unwrap();
+            answer(theirs, stream, their_key)
+        });
+        let ours = connect(ours, &address, our_key);
+        (ours, peer.join().unwrap())
+    })
+}
+
+#[test]
+fn both_sides_end_up_with_the_changes_of_both() {
+    let key = Key::for_sync("s3cret").unwrap();
+    let mut ours = group();
+    let mut theirs = ours.clone();
+    add(&mut ours, "Sara", 12.0, "coffee");
+    add(&mut theirs, "Ali", 8.0, "bread");
+
+    let (our_report, their_report) = sync(&ours, &key, &theirs, &key);
+    let (Ok(our_report), Ok(their_report)) = (our_report, their_report) else {
+        panic!("the sync failed");
+    };
+    assert_eq!((our_report.received, our_report.sent), (1, 1));
+    assert!(our_report.changed && their_report.changed);
+    assert_eq!(descriptions(&our_report.users), ["bread", "coffee", "taxi"]);
+    assert_eq!(
+        descriptions(&their_report.users),
+        descriptions(&our_report.users)
+    );
+    // Each side only appended to its log.
+    assert!(our_report.users.events().starts_with(ours.events()));
+    assert!(their_report.users.events().starts_with(theirs.events()));
+
+    // Once in step, syncing again has nothing to do.
+    let (again, _) = sync(&our_report.users, &key, &their_report.users, &key);
+    let Ok(again) = again else {
+        panic!("the second sync failed");
+    };
+    assert!(!again.changed);
+    assert_eq!((again.received, again.sent), (0, 0));
+}
+
+#[test]
+fn a_peer_without_the_secret_gets_nothing() {
+    let ours = group();
+    let mut theirs = ours.clone();
+    add(&mut theirs, "Ali", 8.0, "bread");
+
+    let (our_report, their_report) = sync(
+        &ours,
+        &Key::for_sync("guess").unwrap(),
+        &theirs,
+        &Key::for_sync("s3cret").unwrap(),
+    );
+    assert!(our_report.is_err());
+    assert!(matches!(their_report, Err(e) if e.contains("secret")));
+}